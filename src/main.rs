@@ -6,6 +6,22 @@ use crate::ui::shares::config::config_file_default;
 use eframe::egui::{self, IconData, RichText, global_theme_preference_buttons};
 #[tokio::main]
 async fn main() -> eframe::Result {
+    // Headless mode for a systemd timer or cron job: sync subscriptions and exit.
+    if std::env::args().any(|a| a == "--sync-subscriptions") {
+        config_file_default();
+        ui::shares::subscriptions::sync_due(false);
+        return Ok(());
+    }
+    if std::env::args().any(|a| a == "--retry-lyrics") {
+        config_file_default();
+        match ui::shares::config::load_config(&ui::shares::config::get_config_file_path()) {
+            Ok(configs) => {
+                ui::shares::lyrics_providers::retry_missing(&configs);
+            }
+            Err(e) => eprintln!("lyrics: Fail to read config {e}"),
+        }
+        return Ok(());
+    }
     let icon = include_bytes!("../assets/logo.png").to_vec();
     let icon = IconData {
         rgba: icon,
@@ -28,10 +44,15 @@ async fn main() -> eframe::Result {
 
 struct MainApp {
     music_download: ui::music_dl::MusicDownload,
+    lyrics_editor: ui::lyrics_editor::LyricsEditor,
+    musicbrainz_review: ui::musicbrainz_review::MusicBrainzReview,
     video_download: ui::video_dl::VideoDownload,
     pinterest_download: ui::pinterest::PinterstDownload,
     image_convert: ui::img_convert::ImgConvert,
     video_convert: ui::video_convert::VideoConvert,
+    subscriptions: ui::subscriptions::Subscriptions,
+    network: ui::shares::network::Network,
+    cookie_form: ui::shares::cookies::CookieForm,
     run_on_start: bool,
     yt: bool,
     ffmpeg: bool,
@@ -43,10 +64,15 @@ impl Default for MainApp {
     fn default() -> Self {
         Self {
             music_download: ui::music_dl::MusicDownload::default(),
+            lyrics_editor: ui::lyrics_editor::LyricsEditor::default(),
+            musicbrainz_review: ui::musicbrainz_review::MusicBrainzReview::default(),
             video_download: ui::video_dl::VideoDownload::default(),
             pinterest_download: ui::pinterest::PinterstDownload::default(),
             image_convert: ui::img_convert::ImgConvert::default(),
             video_convert: ui::video_convert::VideoConvert::default(),
+            subscriptions: ui::subscriptions::Subscriptions::default(),
+            network: ui::shares::network::settings(),
+            cookie_form: ui::shares::cookies::CookieForm::default(),
             run_on_start: false,
             yt: true,
             ffmpeg: false,
@@ -65,6 +91,10 @@ impl eframe::App for MainApp {
         if !self.run_on_start {
             config_file_default();
             self.check_result = version_check::version_check();
+            ui::shares::subscriptions::start_scheduler();
+            ui::shares::lyrics_providers::start_retry_scheduler();
+            ui::shares::musicbrainz::enable_review();
+            std::thread::spawn(ui::shares::musicbrainz_client::prune);
             self.run_on_start = true;
         };
 
@@ -90,6 +120,12 @@ impl eframe::App for MainApp {
                 ui.heading("Azul Box");
                 ui.horizontal_wrapped(|ui| {
                     global_theme_preference_buttons(ui);
+                    ui.menu_button("Network", |ui| {
+                        ui::shares::network::network_menu(ui, &mut self.network);
+                    });
+                    ui.menu_button("Cookies", |ui| {
+                        ui::shares::cookies::cookies_menu(ui, &mut self.cookie_form);
+                    });
                     let mut toasts = Toasts::new()
                         .anchor(Align2::RIGHT_BOTTOM, (-10.0, -10.0)) // 10 units from the bottom right corner
                         .direction(egui::Direction::BottomUp);
@@ -154,6 +190,17 @@ impl eframe::App for MainApp {
                 .default_open(false)
                 .resizable(false)
                 .show(ctx, |ui| self.music_download.ui(ui));
+            //Lyrics
+            egui::Window::new("Lyrics editor")
+                .default_open(false)
+                .resizable(false)
+                .show(ctx, |ui| self.lyrics_editor.ui(ui));
+            //MusicBrainz review, only while tracks wait for it
+            if self.musicbrainz_review.poll() {
+                egui::Window::new("MusicBrainz review")
+                    .resizable(false)
+                    .show(ctx, |ui| self.musicbrainz_review.ui(ui));
+            }
             //Video
             egui::Window::new("Video-dl")
                 .default_open(false)
@@ -161,6 +208,13 @@ impl eframe::App for MainApp {
                 .show(ctx, |ui| {
                     self.video_download.ui(ui);
                 });
+            //Subscriptions
+            egui::Window::new("Subscriptions")
+                .default_open(false)
+                .resizable(false)
+                .show(ctx, |ui| {
+                    self.subscriptions.ui(ui);
+                });
         }
         if self.pin {
             //Pinterest
@@ -0,0 +1,540 @@
+use crate::ui::shares::lyrics::{self, Lrc, TimedLine};
+use eframe::egui::{self, Color32};
+use native_dialog::DialogBuilder;
+use rodio::{Decoder, OutputStream, Sink};
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+struct EditLine {
+    time: Option<u32>,
+    text: String,
+}
+
+#[derive(PartialEq)]
+enum Mode {
+    Sync,
+    Text,
+}
+
+/// What rodio plays for the open file: the file itself, or a WAV decoded by
+/// ffmpeg for formats rodio can't read. The WAV is removed on drop.
+struct Playable {
+    path: PathBuf,
+    temp: bool,
+}
+
+impl Playable {
+    /// Runs ffmpeg when needed, so keep it off the UI thread.
+    fn prepare(path: &Path) -> Result<Self, String> {
+        if decode(path).is_ok() {
+            return Ok(Self {
+                path: path.to_path_buf(),
+                temp: false,
+            });
+        }
+        Ok(Self {
+            path: to_wav(path)?,
+            temp: true,
+        })
+    }
+}
+
+impl Drop for Playable {
+    fn drop(&mut self) {
+        if self.temp {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+struct Player {
+    _stream: OutputStream,
+    sink: Sink,
+}
+
+impl Player {
+    fn open(audio: &Playable) -> Result<Self, String> {
+        let (stream, handle) = OutputStream::try_default().map_err(|e| e.to_string())?;
+        let sink = Sink::try_new(&handle).map_err(|e| e.to_string())?;
+        sink.append(decode(&audio.path)?);
+        sink.pause();
+        Ok(Self {
+            _stream: stream,
+            sink,
+        })
+    }
+    fn position_ms(&self) -> u32 {
+        self.sink.get_pos().as_millis().min(u32::MAX as u128) as u32
+    }
+    fn seek(&self, ms: u32) {
+        if let Err(e) = self.sink.try_seek(Duration::from_millis(ms as u64)) {
+            println!("lyrics_editor: Fail to seek {e}");
+        }
+    }
+}
+
+fn decode(path: &Path) -> Result<Decoder<BufReader<File>>, String> {
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    Decoder::new(BufReader::new(file)).map_err(|e| e.to_string())
+}
+
+fn to_wav(path: &Path) -> Result<PathBuf, String> {
+    // A file opened while another is still decoding gets its own WAV.
+    static NEXT: AtomicU32 = AtomicU32::new(0);
+    let temp = std::env::temp_dir().join(format!(
+        "azulbox-lyrics-{}-{}.wav",
+        std::process::id(),
+        NEXT.fetch_add(1, Ordering::Relaxed)
+    ));
+    let output = Command::new("ffmpeg")
+        .arg("-y")
+        .arg("-i")
+        .arg(path)
+        .arg("-map")
+        .arg("0:a")
+        .arg(&temp)
+        .output()
+        .map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        let _ = fs::remove_file(&temp);
+        return Err(String::from_utf8_lossy(&output.stderr)
+            .lines()
+            .last()
+            .unwrap_or("ffmpeg failed")
+            .to_string());
+    }
+    Ok(temp)
+}
+
+// Decoded in the background, tagged with the file it was asked for.
+type Decoded = (String, Result<Playable, String>);
+
+pub struct LyricsEditor {
+    pub file: String,
+    lines: Vec<EditLine>,
+    tags: Vec<(String, String)>,
+    text: String,
+    mode: Mode,
+    current: usize,
+    scroll: bool,
+    shift_ms: i32,
+    player: Option<Player>,
+    // Kept while the same file stays open, so playing again doesn't decode again.
+    audio: Option<Playable>,
+    decoded: Arc<Mutex<Vec<Decoded>>>,
+    loading: bool,
+    playback_error: String,
+    status: String,
+}
+
+impl Default for LyricsEditor {
+    fn default() -> Self {
+        Self {
+            file: String::new(),
+            lines: Vec::new(),
+            tags: Vec::new(),
+            text: String::new(),
+            mode: Mode::Sync,
+            current: 0,
+            scroll: false,
+            shift_ms: 0,
+            player: None,
+            audio: None,
+            decoded: Arc::new(Mutex::new(Vec::new())),
+            loading: false,
+            playback_error: String::new(),
+            status: String::new(),
+        }
+    }
+}
+
+impl LyricsEditor {
+    fn load(&mut self) {
+        let path = PathBuf::from(&self.file);
+        self.player = None;
+        self.audio = None;
+        self.playback_error.clear();
+        self.loading = true;
+        let decoded = self.decoded.clone();
+        let file = self.file.clone();
+        tokio::task::spawn(async move {
+            let result = Playable::prepare(Path::new(&file));
+            if let Ok(mut decoded) = decoded.lock() {
+                decoded.push((file, result));
+            }
+        });
+        self.current = 0;
+        self.shift_ms = 0;
+        let lrc = lyrics::read(&path).unwrap_or_default();
+        self.tags = lrc.tags.clone();
+        self.lines = if lrc.synced() {
+            lrc.lines
+                .into_iter()
+                .map(|l| EditLine {
+                    time: Some(l.time_ms),
+                    text: l.text,
+                })
+                .collect()
+        } else {
+            lrc.plain
+                .into_iter()
+                .filter(|l| !l.trim().is_empty())
+                .map(|text| EditLine { time: None, text })
+                .collect()
+        };
+        self.text = self.to_text();
+        if self.lines.is_empty() {
+            self.mode = Mode::Text;
+            self.status = "No lyrics found, paste them here".to_string();
+        } else {
+            self.mode = Mode::Sync;
+            self.status = format!("{} lines", self.lines.len());
+        }
+    }
+
+    /// Opens the player once the background decode for this file is done.
+    /// Results for files opened before are dropped with their temp WAV.
+    fn poll_decoded(&mut self) {
+        if !self.loading {
+            return;
+        }
+        let done: Vec<_> = match self.decoded.lock() {
+            Ok(mut decoded) => decoded.drain(..).collect(),
+            Err(_) => return,
+        };
+        for (file, result) in done {
+            if file != self.file {
+                continue;
+            }
+            self.loading = false;
+            match result.and_then(|audio| Ok((Player::open(&audio)?, audio))) {
+                Ok((player, audio)) => {
+                    self.player = Some(player);
+                    self.audio = Some(audio);
+                }
+                Err(e) => self.playback_error = e,
+            }
+        }
+    }
+
+    fn to_text(&self) -> String {
+        let mut out = String::new();
+        for (key, value) in self.tags.iter() {
+            out.push_str(&format!("[{key}:{value}]\n"));
+        }
+        for line in self.lines.iter() {
+            match line.time {
+                Some(t) => out.push_str(&format!("[{}]{}\n", lyrics::stamp(t), line.text)),
+                None => out.push_str(&format!("{}\n", line.text)),
+            }
+        }
+        out
+    }
+
+    fn parse_text(&mut self) {
+        self.tags.clear();
+        self.lines.clear();
+        for line in self.text.lines() {
+            if let Some((time, rest)) = lyrics::parse_stamp(line.trim_start()) {
+                self.lines.push(EditLine {
+                    time: Some(time),
+                    text: rest.trim().to_string(),
+                });
+            } else if let Some((key, value)) = lyrics::parse_tag(line) {
+                // Timing is fixed with the shift instead.
+                if key != "offset" {
+                    self.tags.push((key, value));
+                }
+            } else if !line.trim().is_empty() {
+                self.lines.push(EditLine {
+                    time: None,
+                    text: line.trim().to_string(),
+                });
+            }
+        }
+        self.current = self.current.min(self.lines.len().saturating_sub(1));
+    }
+
+    /// Untimed lines between stamped ones keep the time of the line before.
+    fn to_lrc(&self) -> Lrc {
+        let mut lrc = Lrc {
+            tags: self.tags.clone(),
+            ..Lrc::default()
+        };
+        if self.lines.iter().all(|l| l.time.is_none()) {
+            lrc.plain = self.lines.iter().map(|l| l.text.clone()).collect();
+            return lrc;
+        }
+        let mut last = 0;
+        for line in self.lines.iter() {
+            last = line.time.unwrap_or(last);
+            lrc.lines.push(TimedLine {
+                time_ms: last,
+                text: line.text.clone(),
+            });
+        }
+        lrc.lines.sort_by_key(|l| l.time_ms);
+        lrc
+    }
+
+    fn stamp_current(&mut self) {
+        let Some(player) = &self.player else {
+            return;
+        };
+        if let Some(line) = self.lines.get_mut(self.current) {
+            line.time = Some(player.position_ms());
+            self.current = (self.current + 1).min(self.lines.len() - 1);
+            self.scroll = true;
+        }
+    }
+
+    fn step_back(&mut self) {
+        self.current = self.current.saturating_sub(1);
+        if let Some(line) = self.lines.get_mut(self.current) {
+            line.time = None;
+        }
+        self.scroll = true;
+    }
+
+    fn save(&mut self, to_tags: bool) {
+        if self.mode == Mode::Text {
+            self.parse_text();
+        }
+        let path = PathBuf::from(&self.file);
+        let lrc = self.to_lrc();
+        if to_tags {
+            match lyrics::embed(&path, &lrc, None) {
+                Ok(_) => self.status = "Saved to tags".to_string(),
+                Err(e) => self.status = format!("Fail to save {e}"),
+            }
+        } else {
+            lyrics::write_sidecar(&path, &lrc);
+            self.status = format!("Saved {}", lyrics::sidecar_path(&path).display());
+        }
+    }
+
+    // The last stamped line at or before the playback position.
+    fn playing_line(&self) -> Option<usize> {
+        let pos = self.player.as_ref()?.position_ms();
+        self.lines
+            .iter()
+            .enumerate()
+            .filter(|(_, l)| l.time.is_some_and(|t| t <= pos))
+            .max_by_key(|(_, l)| l.time)
+            .map(|(i, _)| i)
+    }
+
+    fn player_bar(&mut self, ui: &mut egui::Ui) {
+        self.poll_decoded();
+        let mut reopen = false;
+        ui.horizontal(|ui| {
+            let Some(player) = &self.player else {
+                if self.loading {
+                    ui.spinner();
+                    ui.label("Loading audio...");
+                    ui.ctx().request_repaint_after(Duration::from_millis(100));
+                } else if !self.playback_error.is_empty() {
+                    ui.label(format!("Can't play this file: {}", self.playback_error));
+                } else {
+                    ui.label("No playback");
+                }
+                return;
+            };
+            let paused = player.sink.is_paused();
+            if ui.button(if paused { "Play" } else { "Pause" }).clicked() {
+                if player.sink.empty() {
+                    // Played to the end, the source is gone.
+                    reopen = true;
+                } else if paused {
+                    player.sink.play();
+                } else {
+                    player.sink.pause();
+                }
+            }
+            let pos = player.position_ms();
+            if ui.button("-5s").clicked() {
+                player.seek(pos.saturating_sub(5000));
+            }
+            if ui.button("+5s").clicked() {
+                player.seek(pos + 5000);
+            }
+            ui.label(lyrics::stamp(pos));
+            if !paused {
+                ui.ctx().request_repaint_after(Duration::from_millis(50));
+            }
+        });
+        if reopen && let Some(audio) = &self.audio {
+            self.player = None;
+            match Player::open(audio) {
+                Ok(player) => {
+                    player.sink.play();
+                    self.player = Some(player);
+                }
+                Err(e) => self.playback_error = e,
+            }
+        }
+    }
+
+    fn sync_view(&mut self, ui: &mut egui::Ui) {
+        ui.small("Space: stamp the line and go on, Backspace: back one line");
+        let typing = ui.ctx().memory(|m| m.focused().is_some());
+        if !typing {
+            if ui.input(|i| i.key_pressed(egui::Key::Space)) {
+                self.stamp_current();
+            }
+            if ui.input(|i| i.key_pressed(egui::Key::Backspace)) {
+                self.step_back();
+            }
+        }
+        let playing = self.playing_line();
+        let mut seek = None;
+        egui::ScrollArea::vertical()
+            .max_height(350.0)
+            .show(ui, |ui| {
+                for (i, line) in self.lines.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        let time = line
+                            .time
+                            .map(lyrics::stamp)
+                            .unwrap_or_else(|| "--:--.--".to_string());
+                        if ui
+                            .add_enabled(line.time.is_some(), egui::Button::new(time).small())
+                            .on_hover_text("Play from here")
+                            .clicked()
+                        {
+                            seek = line.time;
+                        }
+                        let text = if Some(i) == playing {
+                            egui::RichText::new(&line.text).color(Color32::LIGHT_BLUE)
+                        } else {
+                            egui::RichText::new(&line.text)
+                        };
+                        let resp = ui.selectable_label(i == self.current, text);
+                        if resp.clicked() {
+                            self.current = i;
+                        }
+                        if self.scroll && i == self.current {
+                            resp.scroll_to_me(Some(egui::Align::Center));
+                        }
+                    });
+                }
+            });
+        self.scroll = false;
+        if let (Some(ms), Some(player)) = (seek, &self.player) {
+            player.seek(ms);
+        }
+        ui.horizontal(|ui| {
+            if ui.button("Stamp").clicked() {
+                self.stamp_current();
+            }
+            if ui.button("Back").clicked() {
+                self.step_back();
+            }
+            if ui.button("Clear times").clicked() {
+                for line in self.lines.iter_mut() {
+                    line.time = None;
+                }
+                self.current = 0;
+                self.scroll = true;
+            }
+        });
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("File: ");
+            ui.label(if self.file.is_empty() {
+                "none"
+            } else {
+                &self.file
+            });
+            if ui.button("Open").clicked() {
+                let path = DialogBuilder::file()
+                    .add_filter(
+                        "Audio",
+                        ["mp3", "flac", "opus", "ogg", "m4a", "wav", "aiff", "wv"],
+                    )
+                    .open_single_file()
+                    .show()
+                    .unwrap();
+                if let Some(p) = path {
+                    self.file = p.to_string_lossy().into_owned();
+                    self.load();
+                }
+            }
+        });
+        if self.file.is_empty() {
+            return;
+        }
+        self.player_bar(ui);
+        ui.horizontal(|ui| {
+            let before = self.mode == Mode::Text;
+            ui.radio_value(&mut self.mode, Mode::Sync, "Sync");
+            ui.radio_value(&mut self.mode, Mode::Text, "Text");
+            let text = self.mode == Mode::Text;
+            if before && !text {
+                self.parse_text();
+            } else if !before && text {
+                self.text = self.to_text();
+            }
+        });
+        ui.separator();
+        match self.mode {
+            Mode::Sync => self.sync_view(ui),
+            Mode::Text => {
+                egui::ScrollArea::vertical()
+                    .max_height(350.0)
+                    .show(ui, |ui| {
+                        ui.add(
+                            egui::TextEdit::multiline(&mut self.text)
+                                .code_editor()
+                                .desired_rows(14)
+                                .desired_width(f32::INFINITY),
+                        );
+                    });
+            }
+        }
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.label("Shift: ")
+                .on_hover_text("Negative shows the lyrics earlier");
+            ui.add(
+                egui::DragValue::new(&mut self.shift_ms)
+                    .range(-10000..=10000)
+                    .speed(10)
+                    .suffix(" ms"),
+            );
+            if ui
+                .add_enabled(self.shift_ms != 0, egui::Button::new("Apply"))
+                .clicked()
+            {
+                if self.mode == Mode::Text {
+                    self.parse_text();
+                }
+                for line in self.lines.iter_mut() {
+                    if let Some(t) = line.time.as_mut() {
+                        *t = (*t as i64 + self.shift_ms as i64).max(0) as u32;
+                    }
+                }
+                self.text = self.to_text();
+                self.shift_ms = 0;
+            }
+        });
+        ui.horizontal(|ui| {
+            if ui.button("Save to tags").clicked() {
+                self.save(true);
+            }
+            if ui.button("Save .lrc").clicked() {
+                self.save(false);
+            }
+        });
+        if !self.status.is_empty() {
+            ui.label(&self.status);
+        }
+    }
+}
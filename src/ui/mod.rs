@@ -1,6 +1,9 @@
 pub mod img_convert;
+pub mod lyrics_editor;
 pub mod music_dl;
+pub mod musicbrainz_review;
 pub mod pinterest;
 pub mod shares;
+pub mod subscriptions;
 pub mod video_convert;
 pub mod video_dl;
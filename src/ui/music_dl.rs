@@ -1,29 +1,64 @@
+use crate::ui::shares::album_split;
+use crate::ui::shares::archive::{self, ArchiveEntry, ArchiveTool};
+use crate::ui::shares::audio_format::{self, AudioFormat, Qualities};
+use crate::ui::shares::cleanup::{self, Cleanup, Preview};
+use crate::ui::shares::cookies;
+use crate::ui::shares::cover::{self, CoverSettings};
 use crate::ui::shares::lang::LangThing;
-use crate::ui::shares::lrclib::lrclib_fetch;
-use crate::ui::shares::musicbrainz::musicbrain_work;
+use crate::ui::shares::lyrics_providers::{self, LyricsJob, LyricsProviders};
+use crate::ui::shares::metadata::{self, MetaRule};
+use crate::ui::shares::musicbrainz::{self, MusicBrainzJob, ReleasePolicy, musicbrain_work};
+use crate::ui::shares::musicbrainz_client;
+use crate::ui::shares::network;
 use crate::ui::shares::notify::{
     button_sound, done_sound, fail_sound, notification_done, notification_fail,
 };
+use crate::ui::shares::process::Running;
+use crate::ui::shares::sections;
+use crate::ui::shares::source_audio::{self, SourceState};
+use crate::ui::shares::sponsorblock::{self, SponsorBlock};
+use crate::ui::shares::translate::{self, TranslateSettings};
 use eframe::egui::{self, Color32};
+use lofty::picture::MimeType;
 use native_dialog::DialogBuilder;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::process::Command;
-use std::sync::Arc;
 use std::sync::atomic::{AtomicI8, Ordering};
+use std::sync::{Arc, Mutex};
 
 pub struct MusicDownload {
     pub link: String,
     pub out_directory: String,
     pub status: Arc<AtomicI8>,
-    pub format: i8,
+    pub format: AudioFormat,
+    pub qualities: Qualities,
     pub lyrics: bool,
     pub frag: i8,
     pub sub_lang: String,
     pub auto_lyric: bool,
     pub sim_rate: i8,
     pub musicbrainz: bool,
-    pub lrclib: bool,
+    pub musicbrainz_review: bool,
+    pub auto_accept: i8,
+    pub release_policy: ReleasePolicy,
+    pub providers: LyricsProviders,
+    pub lrc_sidecar: bool,
+    pub translate: TranslateSettings,
+    pub lyrics_retry_hours: u32,
+    pub archive: bool,
+    pub sponsorblock: SponsorBlock,
+    pub sponsorblock_api: String,
+    pub sections: String,
+    pub exact_cuts: bool,
+    pub album: bool,
+    pub tracklist: String,
+    pub metadata: Vec<MetaRule>,
+    pub cleanup: Cleanup,
+    pub cleanup_preview: Preview,
+    pub cover: CoverSettings,
+    pub source: Arc<Mutex<SourceState>>,
+    pub running: Running,
     pub config_path: PathBuf,
 }
 
@@ -45,24 +80,286 @@ impl Default for MusicDownload {
         Self {
             link: String::new(),
             out_directory: default_directory,
-            status: Arc::new(AtomicI8::new(0)), // 0 = nothing / 1 = pending / 2 = Done / 3 = Fail
+            status: Arc::new(AtomicI8::new(0)), // 0 = nothing / 1 = pending / 2 = Done / 3 = Fail / 4 = Needs login
             format: configs.music_dl.format,
+            qualities: configs.music_dl.qualities,
             lyrics: configs.music_dl.lyrics,
             frag: configs.music_dl.fragments,
             sub_lang: configs.universal.language,
             auto_lyric: configs.music_dl.auto_gen_sub,
             sim_rate: configs.music_dl.threshold,
             musicbrainz: configs.music_dl.musicbrainz,
-            lrclib: configs.music_dl.liblrc,
+            musicbrainz_review: configs.music_dl.musicbrainz_review,
+            auto_accept: configs.music_dl.auto_accept,
+            release_policy: configs.music_dl.release_policy,
+            providers: configs
+                .music_dl
+                .providers
+                .or_legacy(configs.music_dl.liblrc),
+            lrc_sidecar: configs.music_dl.lrc_sidecar,
+            translate: configs.music_dl.translate,
+            lyrics_retry_hours: configs.music_dl.lyrics_retry_hours,
+            archive: configs.music_dl.archive,
+            sponsorblock: configs.music_dl.sponsorblock,
+            sponsorblock_api: configs.universal.sponsorblock_api,
+            sections: String::new(),
+            exact_cuts: configs.music_dl.exact_cuts,
+            album: false,
+            tracklist: String::new(),
+            metadata: configs.music_dl.metadata,
+            cleanup: configs.music_dl.cleanup,
+            cleanup_preview: Preview::default(),
+            cover: configs.music_dl.cover,
+            source: Arc::new(Mutex::new(SourceState::Idle)),
+            running: Running::default(),
             config_path: path,
         }
     }
 }
 
 impl MusicDownload {
+    fn retry_lyrics(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Retry missing every: ");
+            let drag = ui
+                .add(
+                    egui::DragValue::new(&mut self.lyrics_retry_hours)
+                        .range(0..=720)
+                        .suffix(" h"),
+                )
+                .on_hover_text("0 = never");
+            if drag.changed() {
+                match config::modifier_config(&self.config_path, |cfg| {
+                    cfg.music_dl.lyrics_retry_hours = self.lyrics_retry_hours
+                }) {
+                    Ok(_) => {
+                        println!("music_dl: Changed lyrics retry")
+                    }
+                    Err(e) => {
+                        println!("music_dl: Fail change lyrics retry {e}")
+                    }
+                }
+            }
+        });
+        let retrying = lyrics_providers::is_retrying();
+        let label = if retrying {
+            "Retrying..."
+        } else {
+            "Retry missing lyrics"
+        };
+        let button = ui
+            .add_enabled(!retrying, egui::Button::new(label))
+            .on_hover_text("Ask the providers again for songs saved without lyrics");
+        if button.clicked() {
+            tokio::task::spawn(async move {
+                match config::load_config(&config::get_config_file_path()) {
+                    Ok(configs) => {
+                        if let Some(found) = lyrics_providers::retry_missing(&configs) {
+                            println!("music_dl: Retry found {found} lyrics");
+                        }
+                    }
+                    Err(e) => println!("music_dl: Fail to read config {e}"),
+                }
+            });
+        }
+    }
+
     fn start_download_status(&mut self) {
         self.status.store(1, Ordering::Relaxed);
     }
+    fn job(&self) -> MusicJob {
+        MusicJob {
+            link: self.link.clone(),
+            directory: self.out_directory.clone(),
+            format: self.format,
+            qualities: self.qualities.clone(),
+            lyrics: self.lyrics,
+            frags: self.frag,
+            lang_code: self.sub_lang.clone(),
+            auto_lyric: self.auto_lyric,
+            sim_rate: self.sim_rate,
+            musicbrainz: self.musicbrainz,
+            musicbrainz_review: self.musicbrainz_review,
+            auto_accept: self.auto_accept,
+            release_policy: self.release_policy.clone(),
+            providers: self.providers.clone(),
+            lrc_sidecar: self.lrc_sidecar,
+            translate: self.translate.clone(),
+            archive: self.archive,
+            sponsorblock: self.sponsorblock.clone(),
+            sponsorblock_api: self.sponsorblock_api.clone(),
+            // Split points are timed against the whole video.
+            sections: if self.album {
+                Vec::new()
+            } else {
+                sections::parse_sections(&self.sections).unwrap_or_default()
+            },
+            exact_cuts: self.exact_cuts,
+            album: self.album,
+            tracklist: self.tracklist.clone(),
+            metadata: self.metadata.clone(),
+            cleanup: self.cleanup.clone(),
+            cover: self.cover.clone(),
+            notify: true,
+            running: self.running.clone(),
+        }
+    }
+    fn sponsorblock_button(&mut self, ui: &mut egui::Ui) {
+        ui.menu_button("SponsorBlock", |ui| {
+            let mut api_changed = false;
+            if sponsorblock::sponsorblock_menu(
+                ui,
+                &mut self.sponsorblock,
+                &mut self.sponsorblock_api,
+                &mut api_changed,
+            ) {
+                match config::modifier_config(&self.config_path, |cfg| {
+                    cfg.music_dl.sponsorblock = self.sponsorblock.clone()
+                }) {
+                    Ok(_) => {
+                        println!("music_dl: Changed sponsorblock")
+                    }
+                    Err(e) => {
+                        println!("music_dl: Fail change sponsorblock {e}")
+                    }
+                }
+            }
+            if api_changed {
+                match config::modifier_config(&self.config_path, |cfg| {
+                    cfg.universal.sponsorblock_api = self.sponsorblock_api.clone()
+                }) {
+                    Ok(_) => {
+                        println!("music_dl: Changed sponsorblock api")
+                    }
+                    Err(e) => {
+                        println!("music_dl: Fail change sponsorblock api {e}")
+                    }
+                }
+            }
+        });
+    }
+    fn metadata_button(&mut self, ui: &mut egui::Ui) {
+        ui.menu_button("Metadata", |ui| {
+            if metadata::metadata_menu(ui, &mut self.metadata) {
+                match config::modifier_config(&self.config_path, |cfg| {
+                    cfg.music_dl.metadata = self.metadata.clone()
+                }) {
+                    Ok(_) => {
+                        println!("music_dl: Changed metadata rules")
+                    }
+                    Err(e) => {
+                        println!("music_dl: Fail change metadata rules {e}")
+                    }
+                }
+            }
+        });
+    }
+    fn cleanup_button(&mut self, ui: &mut egui::Ui) {
+        ui.menu_button("Cleanup", |ui| {
+            if cleanup::cleanup_menu(ui, &mut self.cleanup, &mut self.cleanup_preview) {
+                match config::modifier_config(&self.config_path, |cfg| {
+                    cfg.music_dl.cleanup = self.cleanup.clone()
+                }) {
+                    Ok(_) => {
+                        println!("music_dl: Changed cleanup")
+                    }
+                    Err(e) => {
+                        println!("music_dl: Fail change cleanup {e}")
+                    }
+                }
+            }
+        });
+    }
+    // What the link's best audio stream really is, and whether the chosen format suits it.
+    fn source_line(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            let loading = matches!(*self.source.lock().unwrap(), SourceState::Loading);
+            if ui
+                .add_enabled(
+                    !self.link.trim().is_empty() && !loading,
+                    egui::Button::new("Check source"),
+                )
+                .clicked()
+            {
+                *self.source.lock().unwrap() = SourceState::Loading;
+                let source = self.source.clone();
+                let link = self.link.trim().to_string();
+                tokio::task::spawn(async move {
+                    let result = match source_audio::probe(&link) {
+                        Ok(audio) => SourceState::Ready(audio),
+                        Err(e) => SourceState::Failed(e),
+                    };
+                    *source.lock().unwrap() = result;
+                });
+            }
+            match &*self.source.lock().unwrap() {
+                SourceState::Idle => {}
+                SourceState::Loading => {
+                    ui.spinner();
+                }
+                SourceState::Failed(e) => {
+                    ui.colored_label(Color32::LIGHT_RED, e);
+                }
+                SourceState::Ready(audio) => {
+                    ui.label(format!("Source: {}", audio.describe()));
+                    if self.format == AudioFormat::Original {
+                        ui.label(format!("kept as {}", audio.container()));
+                    } else if self.format.lossless() && !audio.lossless() {
+                        ui.colored_label(
+                            Color32::YELLOW,
+                            "Lossy source: a lossless format only makes the file bigger",
+                        );
+                    }
+                }
+            }
+            // Most sites only serve lossy audio, so warn until the source says otherwise.
+            if self.format.lossless()
+                && !matches!(*self.source.lock().unwrap(), SourceState::Ready(_))
+            {
+                ui.colored_label(
+                    Color32::YELLOW,
+                    "Sources are usually lossy: a lossless format only makes the file bigger",
+                );
+            }
+        });
+    }
+    fn cover_button(&mut self, ui: &mut egui::Ui) {
+        ui.menu_button("Cover", |ui| {
+            if cover::cover_menu(ui, &mut self.cover) {
+                match config::modifier_config(&self.config_path, |cfg| {
+                    cfg.music_dl.cover = self.cover.clone()
+                }) {
+                    Ok(_) => {
+                        println!("music_dl: Changed cover")
+                    }
+                    Err(e) => {
+                        println!("music_dl: Fail change cover {e}")
+                    }
+                }
+            }
+        });
+    }
+    fn archive_button(&mut self, ui: &mut egui::Ui) {
+        ui.menu_button("Archive", |ui| {
+            ui.horizontal(|ui| {
+                ui.label("On/Off: ");
+                let check = ui.checkbox(&mut self.archive, "");
+                if check.changed() {
+                    match config::modifier_config(&self.config_path, |cfg| {
+                        cfg.music_dl.archive = self.archive
+                    }) {
+                        Ok(_) => {
+                            println!("music_dl: Changed archive")
+                        }
+                        Err(e) => {
+                            println!("music_dl: Fail change archive {e}")
+                        }
+                    }
+                }
+            });
+            archive::archive_ui(ui, ArchiveTool::Music);
+        });
+    }
     fn music_brainz_button(&mut self, ui: &mut egui::Ui) {
         ui.menu_button("Musicbrainz", |ui| {
             ui.horizontal(|ui| {
@@ -96,34 +393,103 @@ impl MusicDownload {
                     }
                 }
             }
+            let check = ui
+                .checkbox(&mut self.musicbrainz_review, "Review unsure matches")
+                .on_hover_text("Pick the match yourself when it is ambiguous or below auto-accept");
+            if check.changed() {
+                match config::modifier_config(&self.config_path, |cfg| {
+                    cfg.music_dl.musicbrainz_review = self.musicbrainz_review
+                }) {
+                    Ok(_) => {
+                        println!("music_dl: Changed musicbrainz review")
+                    }
+                    Err(e) => {
+                        println!("music_dl: Fail change musicbrainz review {e}")
+                    }
+                }
+            }
+            ui.add_enabled_ui(self.musicbrainz_review, |ui| {
+                let slider =
+                    egui::widgets::Slider::new(&mut self.auto_accept, 0..=100).text("Auto-accept");
+                if ui.add(slider).changed() {
+                    match config::modifier_config(&self.config_path, |cfg| {
+                        cfg.music_dl.auto_accept = self.auto_accept
+                    }) {
+                        Ok(_) => {
+                            println!("music_dl: Changed auto-accept")
+                        }
+                        Err(e) => {
+                            println!("music_dl: Fail change auto-accept {e}")
+                        }
+                    }
+                }
+            });
+            ui.menu_button("Release preference", |ui| {
+                if musicbrainz::release_policy_menu(ui, &mut self.release_policy) {
+                    match config::modifier_config(&self.config_path, |cfg| {
+                        cfg.music_dl.release_policy = self.release_policy.clone()
+                    }) {
+                        Ok(_) => {
+                            println!("music_dl: Changed release preference")
+                        }
+                        Err(e) => {
+                            println!("music_dl: Fail change release preference {e}")
+                        }
+                    }
+                }
+            });
+            if ui
+                .button("Clear cache")
+                .on_hover_text("Forget cached MusicBrainz answers and cover art")
+                .clicked()
+            {
+                musicbrainz_client::clear();
+            }
         });
     }
-    fn format_button(&mut self, ui: &mut egui::Ui, name: &str, numbername: i8) {
-        if self.format == numbername {
+    fn format_button(&mut self, ui: &mut egui::Ui, format: AudioFormat) {
+        let name = format.name();
+        if self.format == format {
             if ui
                 .add(egui::Button::new(
                     egui::RichText::new(name).color(Color32::LIGHT_BLUE),
                 ))
                 .clicked()
             {
-                self.format = numbername;
                 ui.close_menu();
             };
-        } else {
-            if ui.button(name).clicked() {
-                self.format = numbername;
+        } else if ui.button(name).clicked() {
+            self.format = format;
+            match config::modifier_config(&self.config_path, |cfg| {
+                cfg.music_dl.format = self.format
+            }) {
+                Ok(_) => {
+                    println!("music_dl: Changed format")
+                }
+                Err(e) => {
+                    println!("music_dl: Fail change format {e}")
+                }
+            }
+            ui.close_menu();
+        }
+    }
+    fn quality_line(&mut self, ui: &mut egui::Ui) {
+        let format = self.format;
+        if let Some(quality) = self.qualities.get_mut(format) {
+            ui.separator();
+            ui.label(format!("{} quality", format.name()));
+            if audio_format::quality_ui(ui, quality) {
                 match config::modifier_config(&self.config_path, |cfg| {
-                    cfg.music_dl.format = self.format
+                    cfg.music_dl.qualities = self.qualities.clone()
                 }) {
                     Ok(_) => {
-                        println!("music_dl: Changed format")
+                        println!("music_dl: Changed quality")
                     }
                     Err(e) => {
-                        println!("music_dl: Fail change format {e}")
+                        println!("music_dl: Fail change quality {e}")
                     }
                 }
-                ui.close_menu();
-            };
+            }
         }
     }
     fn auto_on(&mut self, ui: &mut egui::Ui) {
@@ -146,38 +512,32 @@ impl MusicDownload {
                     }
                 }
             }
-        } else {
-            if ui.button("Auto generated").clicked() {
-                self.auto_lyric = true;
-                match config::modifier_config(&self.config_path, |cfg| {
-                    cfg.music_dl.auto_gen_sub = self.auto_lyric
-                }) {
-                    Ok(_) => {
-                        println!("music_dl: Changed auto lyric")
-                    }
-                    Err(e) => {
-                        println!("music_dl: Fail change auto lyric {e}")
-                    }
+        } else if ui.button("Auto generated").clicked() {
+            self.auto_lyric = true;
+            match config::modifier_config(&self.config_path, |cfg| {
+                cfg.music_dl.auto_gen_sub = self.auto_lyric
+            }) {
+                Ok(_) => {
+                    println!("music_dl: Changed auto lyric")
+                }
+                Err(e) => {
+                    println!("music_dl: Fail change auto lyric {e}")
                 }
             }
         }
     }
 
     pub fn ui(&mut self, ui: &mut egui::Ui) {
-        if self.format == 5 {
-            self.lyrics = false;
-        }
         ui.horizontal(|ui| {
             ui.menu_button("Setting", |ui| {
                 ui.menu_button("Format", |ui| {
-                    self.format_button(ui, "OPUS", 1);
-                    self.format_button(ui, "FLAC", 2);
-                    self.format_button(ui, "MP3", 3);
-                    self.format_button(ui, "M4A", 4);
-                    self.format_button(ui, "WAV", 5);
+                    for format in AudioFormat::ALL {
+                        self.format_button(ui, format);
+                    }
+                    self.quality_line(ui);
                 });
                 ui.menu_button("Lyrics", |ui| {
-                    if self.lyrics && self.format != 5 {
+                    if self.lyrics {
                         ui.horizontal(|ui| {
                             ui.label("On/Off: ");
                             let check = ui.checkbox(&mut self.lyrics, "");
@@ -199,20 +559,52 @@ impl MusicDownload {
                         self.sub_lang = LangThing::lang_chooser(ui, lang_in);
                         self.auto_on(ui);
                         ui.separator();
-                        let check = ui.checkbox(&mut self.lrclib, "Liblrc lyrics");
+                        ui.menu_button("Providers", |ui| {
+                            if lyrics_providers::providers_menu(ui, &mut self.providers) {
+                                match config::modifier_config(&self.config_path, |cfg| {
+                                    cfg.music_dl.providers = self.providers.clone()
+                                }) {
+                                    Ok(_) => {
+                                        println!("music_dl: Changed lyrics providers")
+                                    }
+                                    Err(e) => {
+                                        println!("music_dl: Fail change lyrics providers {e}")
+                                    }
+                                }
+                            }
+                        });
+                        let check = ui
+                            .checkbox(&mut self.lrc_sidecar, "Keep .lrc files")
+                            .on_hover_text("Many players read lyrics from a file next to the song");
                         if check.changed() {
                             match config::modifier_config(&self.config_path, |cfg| {
-                                cfg.music_dl.liblrc = self.lrclib
+                                cfg.music_dl.lrc_sidecar = self.lrc_sidecar
                             }) {
                                 Ok(_) => {
-                                    println!("music_dl: Changed lrclib")
+                                    println!("music_dl: Changed lrc sidecar")
                                 }
                                 Err(e) => {
-                                    println!("music_dl: Fail change lrclib {e}")
+                                    println!("music_dl: Fail change lrc sidecar {e}")
                                 }
                             }
                         }
-                    } else if self.format != 5 {
+                        ui.menu_button("Translation", |ui| {
+                            if translate::translate_menu(ui, &mut self.translate) {
+                                match config::modifier_config(&self.config_path, |cfg| {
+                                    cfg.music_dl.translate = self.translate.clone()
+                                }) {
+                                    Ok(_) => {
+                                        println!("music_dl: Changed translation")
+                                    }
+                                    Err(e) => {
+                                        println!("music_dl: Fail change translation {e}")
+                                    }
+                                }
+                            }
+                        });
+                        ui.separator();
+                        self.retry_lyrics(ui);
+                    } else {
                         ui.horizontal(|ui| {
                             ui.label("On/Off: ");
                             let check = ui.checkbox(&mut self.lyrics, "");
@@ -232,6 +624,11 @@ impl MusicDownload {
                     }
                 });
                 self.music_brainz_button(ui);
+                self.sponsorblock_button(ui);
+                self.archive_button(ui);
+                self.metadata_button(ui);
+                self.cleanup_button(ui);
+                self.cover_button(ui);
 
                 let check =
                     ui.add(egui::widgets::Slider::new(&mut self.frag, 1..=10).text("Fragments"));
@@ -258,6 +655,9 @@ impl MusicDownload {
                 ui.colored_label(Color32::LIGHT_GREEN, "Done!");
             } else if self.status.load(Ordering::Relaxed) == 3 {
                 ui.colored_label(Color32::LIGHT_RED, "Fail!");
+            } else if self.status.load(Ordering::Relaxed) == 4 {
+                ui.colored_label(Color32::LIGHT_RED, "Authentication required")
+                    .on_hover_text("Add a cookie profile for this site in Cookies");
             }
         });
         ui.separator();
@@ -266,6 +666,36 @@ impl MusicDownload {
             ui.text_edit_singleline(&mut self.link)
                 .labelled_by(link_label.id);
 
+            self.source_line(ui);
+
+            ui.checkbox(&mut self.album, "Album video (split into tracks)");
+            if self.album {
+                ui.label("Tracklist (optional, uses chapters when empty): ");
+                ui.add(
+                    egui::TextEdit::multiline(&mut self.tracklist)
+                        .desired_rows(3)
+                        .hint_text("0:00 First song\n3:45 Second song"),
+                );
+                if !self.sponsorblock.remove.is_empty() {
+                    ui.small("SponsorBlock removal is skipped for album splits");
+                }
+            }
+
+            if self.album {
+                ui.small("Time ranges don't apply to album splits");
+            } else if sections::sections_ui(ui, &mut self.sections, &mut self.exact_cuts) {
+                match config::modifier_config(&self.config_path, |cfg| {
+                    cfg.music_dl.exact_cuts = self.exact_cuts
+                }) {
+                    Ok(_) => {
+                        println!("music_dl: Changed exact cuts")
+                    }
+                    Err(e) => {
+                        println!("music_dl: Fail change exact cuts {e}")
+                    }
+                }
+            }
+
             let dir_label = ui.label("Directory: ");
             if ui
                 .text_edit_singleline(&mut self.out_directory)
@@ -286,28 +716,20 @@ impl MusicDownload {
             };
 
             if self.status.load(Ordering::Relaxed) != 1 {
-                if ui.button("Download").clicked() {
+                let valid = self.album || sections::parse_sections(&self.sections).is_ok();
+                if ui
+                    .add_enabled(valid, egui::Button::new("Download"))
+                    .clicked()
+                {
                     button_sound();
 
                     self.start_download_status();
 
-                    let link = self.link.clone();
-                    let directory = self.out_directory.clone();
-                    let format = self.format;
+                    let job = self.job();
                     let progress = self.status.clone();
-                    let lyrics = self.lyrics;
-                    let frags = self.frag;
-                    let lang_code = self.sub_lang.clone();
-                    let auto = self.auto_lyric;
-                    let brain = self.musicbrainz;
-                    let sim = self.sim_rate;
-                    let lrclib = self.lrclib;
 
                     tokio::task::spawn(async move {
-                        let status = download(
-                            link, directory, format, lyrics, frags, lang_code, auto, sim, brain,
-                            lrclib,
-                        );
+                        let status = download(job);
                         progress.store(status, Ordering::Relaxed);
                         if status == 2 {
                             done_sound();
@@ -316,283 +738,303 @@ impl MusicDownload {
                         }
                     });
                 }
-            } else if self.status.load(Ordering::Relaxed) == 1 {
-                if ui.button("Cancel").clicked() {
-                    button_sound();
-                    let _ = Command::new("pkill").arg("yt-dlp").output();
-                }
+            } else if self.status.load(Ordering::Relaxed) == 1 && ui.button("Cancel").clicked() {
+                button_sound();
+                self.running.kill();
             }
         });
     }
 }
 
-fn download(
-    link: String,
-    directory: String,
-    format: i8,
-    lyrics: bool,
-    frags: i8,
-    lang_code: String,
-    lyric_auto: bool,
-    sim_rate: i8,
-    musicbrainz: bool,
-    lrclib: bool,
-) -> i8 {
-    if format == 1 {
-        format_dl(
-            link,
-            directory,
-            "opus",
-            lyrics,
-            frags,
-            lang_code,
-            lyric_auto,
-            sim_rate,
-            musicbrainz,
-            lrclib,
-        )
-    } else if format == 2 {
-        format_dl(
-            link,
-            directory,
-            "flac",
-            lyrics,
-            frags,
-            lang_code,
-            lyric_auto,
-            sim_rate,
-            musicbrainz,
-            lrclib,
-        )
-    } else if format == 3 {
-        format_dl(
-            link,
-            directory,
-            "mp3",
-            lyrics,
-            frags,
-            lang_code,
-            lyric_auto,
-            sim_rate,
-            musicbrainz,
-            lrclib,
-        )
-    } else if format == 4 {
-        format_dl(
-            link,
-            directory,
-            "m4a",
-            lyrics,
-            frags,
-            lang_code,
-            lyric_auto,
-            sim_rate,
-            musicbrainz,
-            lrclib,
-        )
-    } else if format == 5 {
-        format_dl(
-            link,
-            directory,
-            "wav",
-            lyrics,
-            frags,
-            lang_code,
-            lyric_auto,
-            sim_rate,
-            musicbrainz,
-            lrclib,
-        )
-    } else {
-        3
+#[derive(Debug, Clone)]
+pub struct MusicJob {
+    pub link: String,
+    pub directory: String,
+    pub format: AudioFormat,
+    pub qualities: Qualities,
+    pub lyrics: bool,
+    pub frags: i8,
+    pub lang_code: String,
+    pub auto_lyric: bool,
+    pub sim_rate: i8,
+    pub musicbrainz: bool,
+    pub musicbrainz_review: bool,
+    pub auto_accept: i8,
+    pub release_policy: ReleasePolicy,
+    pub providers: LyricsProviders,
+    pub lrc_sidecar: bool,
+    pub translate: TranslateSettings,
+    pub archive: bool,
+    pub sponsorblock: SponsorBlock,
+    pub sponsorblock_api: String,
+    pub sections: Vec<String>,
+    pub exact_cuts: bool,
+    pub album: bool,
+    pub tracklist: String,
+    pub metadata: Vec<MetaRule>,
+    pub cleanup: Cleanup,
+    pub cover: CoverSettings,
+    pub notify: bool,
+    pub running: Running,
+}
+
+impl MusicJob {
+    /// Builds a job from the saved Music-dl settings, for downloads started
+    /// outside the window (subscriptions).
+    pub fn from_config(
+        configs: &config::Config,
+        link: &str,
+        directory: &str,
+        format: AudioFormat,
+    ) -> Self {
+        Self {
+            link: link.to_string(),
+            directory: directory.to_string(),
+            format,
+            qualities: configs.music_dl.qualities.clone(),
+            lyrics: configs.music_dl.lyrics,
+            frags: configs.music_dl.fragments,
+            lang_code: configs.universal.language.clone(),
+            auto_lyric: configs.music_dl.auto_gen_sub,
+            sim_rate: configs.music_dl.threshold,
+            musicbrainz: configs.music_dl.musicbrainz,
+            musicbrainz_review: configs.music_dl.musicbrainz_review,
+            auto_accept: configs.music_dl.auto_accept,
+            release_policy: configs.music_dl.release_policy.clone(),
+            providers: configs
+                .music_dl
+                .providers
+                .clone()
+                .or_legacy(configs.music_dl.liblrc),
+            lrc_sidecar: configs.music_dl.lrc_sidecar,
+            translate: configs.music_dl.translate.clone(),
+            archive: true,
+            sponsorblock: configs.music_dl.sponsorblock.clone(),
+            sponsorblock_api: configs.universal.sponsorblock_api.clone(),
+            sections: Vec::new(),
+            exact_cuts: configs.music_dl.exact_cuts,
+            album: false,
+            tracklist: String::new(),
+            metadata: configs.music_dl.metadata.clone(),
+            cleanup: configs.music_dl.cleanup.clone(),
+            cover: configs.music_dl.cover.clone(),
+            notify: false,
+            running: Running::default(),
+        }
     }
 }
 
-fn format_dl(
-    link: String,
-    directory: String,
-    format_name: &str,
-    lyrics: bool,
-    frags: i8,
-    lang_code: String,
-    auto_lyric: bool,
-    sim_rate: i8,
-    musicbrainz: bool,
-    lrclib: bool,
-) -> i8 {
-    let n = frags.to_string();
+pub fn download(job: MusicJob) -> i8 {
+    let n = job.frags.to_string();
     println!("{n}");
 
-    let files: Vec<&str>;
-
     let mut yt = Command::new("yt-dlp");
     yt.arg("--concurrent-fragments")
         .arg(&n)
         .arg("-i")
         .arg("-x")
-        .arg("--audio-quality")
-        .arg("0")
-        .arg("--audio-format")
-        .arg(format_name)
         .arg("--embed-thumbnail")
         .arg("--add-metadata")
-        .arg("--metadata-from-title")
-        .arg("%(title)s")
-        .arg("--parse-metadata")
-        .arg("title:%(title)s")
-        .arg("--parse-metadata")
-        .arg("uploader:%(artist)s")
         .arg("--output")
-        .arg("%(title)s.%(ext)s")
+        .arg(sections::output_template(
+            "%(title)s.%(ext)s",
+            &job.sections,
+        ))
         .arg("--compat-options")
         .arg("no-live-chat")
-        .current_dir(&directory);
+        .current_dir(&job.directory);
+    audio_format::apply(&mut yt, job.format, &job.qualities);
+    metadata::apply(&mut yt, &job.metadata);
+    // A clip isn't the video, a later full download must not be skipped.
+    let archive = job.archive && job.sections.is_empty();
+    let moved = archive::apply(&mut yt, ArchiveTool::Music, archive);
+    let net = network::settings();
+    network::apply_yt_dlp(&mut yt, &net);
+    cookies::apply(&mut yt, &job.link, &net);
+    if job.album {
+        // Cutting segments out would shift every split point after them.
+        let marks_only = SponsorBlock {
+            remove: Vec::new(),
+            mark: job.sponsorblock.mark.clone(),
+        };
+        sponsorblock::apply(&mut yt, &marks_only, &job.sponsorblock_api);
+    } else {
+        sponsorblock::apply(&mut yt, &job.sponsorblock, &job.sponsorblock_api);
+    }
+    sections::apply(&mut yt, &job.sections, job.exact_cuts);
+    if job.album {
+        yt.arg("--write-info-json");
+    }
 
-    let status: i8;
-    if lyrics {
-        if auto_lyric {
+    if job.lyrics {
+        if job.auto_lyric {
             yt.arg("--write-auto-subs");
         }
         yt.arg("--write-subs").arg("--convert-subs").arg("lrc");
 
-        if lang_code != "en" {
-            yt.arg("--sub-langs").arg(&lang_code);
+        if job.lang_code != "en" {
+            yt.arg("--sub-langs").arg(&job.lang_code);
         }
+    }
 
-        yt.arg(&link);
-        let output = yt.output().expect("Failed to execute command");
-
-        let log = String::from_utf8(output.stdout).unwrap_or_else(|_| "Life suck".to_string());
-        println!("{log}");
+    yt.arg(&job.link);
+    let output = job
+        .running
+        .output(&mut yt)
+        .expect("Failed to execute command");
+    let log = String::from_utf8(output.stdout).unwrap_or_else(|_| "Life suck".to_string());
+    let err_log = String::from_utf8_lossy(&output.stderr);
+    println!("{log}");
+    eprintln!("{err_log}");
 
-        files = log
-            .lines()
-            .filter(|line| line.starts_with("[EmbedThumbnail]"))
-            .collect();
-        for i in files.into_iter() {
-            println!("i: {i}");
-            let item = i.split("Adding thumbnail to \"").last().unwrap();
-            println!("item: {item}");
-            let extension = format!(".{}\"", format_name);
-            let filename = &item.split(&extension).next().unwrap();
-            println!("filename: {filename}");
-            let music_file = format!("{}/{}", &directory, &item[0..item.len() - 1].to_string());
-            println!("music dir:{music_file}");
-            lyrics_work(&filename, &music_file, format_name, &directory);
-            let music_file = Path::new(&music_file);
-            if musicbrainz {
-                musicbrain_work(&music_file, sim_rate);
-            }
-            if lrclib {
-                lrclib_fetch(&music_file, &lang_code);
+    let mut files = archive::collect(ArchiveTool::Music, &moved, archive);
+    let lyrics_job = LyricsJob {
+        providers: &job.providers,
+        lang: &job.lang_code,
+        translate: &job.translate,
+        sidecar: job.lrc_sidecar,
+    };
+    let musicbrainz_job = MusicBrainzJob {
+        threshold: job.sim_rate,
+        auto_accept: job.auto_accept,
+        review: job.musicbrainz_review,
+        policy: &job.release_policy,
+    };
+    for entry in files.iter_mut() {
+        println!("music dir:{}", entry.file);
+        let converted = audio_format::convert(Path::new(&entry.file), job.format);
+        if converted != Path::new(&entry.file) {
+            entry.file = converted.to_string_lossy().into_owned();
+            if archive {
+                archive::set_file(ArchiveTool::Music, &entry.key, &entry.file);
             }
         }
-        status = if log.contains("[EmbedThumbnail]") {
-            2
+        // Before splitting, so every track and the folder cover get the processed art.
+        let art = cover::process_file(Path::new(&entry.file), &job.cover);
+        let tracks = if job.album {
+            album_work(&job, entry, art)
         } else {
-            3
+            None
         };
-    } else {
-        yt.arg(&link);
-        let output = yt.output().expect("Failed to execute command");
-        let log = String::from_utf8(output.stdout).unwrap_or_else(|_| "Life suck".to_string());
-        println!("{log}");
-
-        if musicbrainz {
-            files = log
-                .lines()
-                .filter(|line| line.starts_with("[EmbedThumbnail]"))
-                .collect();
-            for i in files.into_iter() {
-                println!("i: {i}");
-                let item = i.split("Adding thumbnail to \"").last().unwrap();
-                println!("item: {item}");
-
-                let music_file = format!("{}/{}", &directory, &item[0..item.len() - 1].to_string());
-                println!("music dir:{music_file}");
-                let music_file = Path::new(&music_file);
-                musicbrain_work(&music_file, sim_rate);
+        let (music_files, sub_lyrics) = match tracks {
+            Some(tracks) => (tracks, None),
+            None => {
+                // Subtitle lyrics cover the whole video, so only use them for single songs.
+                let sub_lyrics = if job.lyrics {
+                    let filename = Path::new(&entry.file)
+                        .file_stem()
+                        .and_then(|s| s.to_str())
+                        .unwrap_or_default();
+                    println!("filename: {filename}");
+                    finder_lyrics(&job.directory, filename)
+                } else {
+                    None
+                };
+                (vec![PathBuf::from(&entry.file)], sub_lyrics)
+            }
+        };
+        for music_file in music_files.iter() {
+            cleanup::tag_file(music_file, &job.cleanup);
+            if job.musicbrainz {
+                musicbrain_work(music_file, &musicbrainz_job);
+                cover::process_file(music_file, &job.cover);
+            }
+            // Lyrics go in last, the other steps would drop the SYLT frame.
+            if job.lyrics {
+                lyrics_providers::work(music_file, sub_lyrics.as_deref(), &lyrics_job);
             }
         }
+    }
 
-        status = if log.contains("[EmbedThumbnail]") {
+    let status: i8 =
+        if !files.is_empty() || log.contains("has already been recorded in the archive") {
             2
+        } else if cookies::auth_required(&err_log) {
+            4
         } else {
             3
         };
-    }
-    if status == 2 {
-        let _ = notification_done("music downloader");
-    } else {
-        let _ = notification_fail("music downloader");
+    if job.notify {
+        if status == 2 {
+            let _ = notification_done("music downloader");
+        } else {
+            let _ = notification_fail("music downloader");
+        }
     }
 
     status
 }
 
-fn lyrics_work(filename: &str, music_file: &str, format_name: &str, directory: &str) {
-    let lyrics_file = finder_lyrics(&directory, &filename).unwrap();
-    let music_file = Path::new(&music_file);
-    let lyrics = match fs::read_to_string(&lyrics_file) {
-        Ok(file) => file,
-        Err(error) => {
-            println!("{:?}", error);
-            "No-1-1!!!F".to_string()
-        }
+// Splits an album-length download by the pasted tracklist or the video's chapters.
+// Returns None (keep the single file) when there is nothing to split by.
+fn album_work(
+    job: &MusicJob,
+    entry: &ArchiveEntry,
+    art: Option<(Vec<u8>, MimeType)>,
+) -> Option<Vec<PathBuf>> {
+    let file = Path::new(&entry.file);
+    let info_path = file.with_extension("info.json");
+    let info: serde_json::Value = fs::read_to_string(&info_path)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default();
+    let _ = fs::remove_file(&info_path);
+
+    let tracks = if job.tracklist.trim().is_empty() {
+        album_split::chapters_from_info(&info)
+    } else {
+        album_split::parse_tracklist(&job.tracklist)
     };
-    if (!(lyrics == "No-1-1!!!F") && format_name == "flac")
-        || (!(lyrics == "No-1-1!!!F") && format_name == "opus")
-        || (!(lyrics == "No-1-1!!!F") && format_name == "mp3")
-        || (!(lyrics == "No-1-1!!!F") && format_name == "m4a")
+    if tracks.len() < 2 {
+        println!("music_dl: No chapters or tracklist to split by");
+        return None;
+    }
+    let album = info
+        .get("album")
+        .or_else(|| info.get("title"))
+        .and_then(|v| v.as_str())
+        .unwrap_or(&entry.title)
+        .to_string();
+    let (folder, files) = album_split::split(file, &tracks, &album)?;
+    // Subtitle lyrics are timed for the whole video, none of the tracks can use them.
+    if let Some(stem) = file.file_stem().and_then(|s| s.to_str())
+        && let Some(dir) = file.parent()
+        && let Ok(entries) = fs::read_dir(dir)
     {
-        use lofty::config::WriteOptions;
-        use lofty::prelude::*;
-        use lofty::probe::Probe;
-        use lofty::tag::Tag;
-
-        let mut tagged_file = Probe::open(&music_file)
-            .expect("ERROR: Bad path provided!")
-            .read()
-            .expect("ERROR: Failed to read file!");
-
-        let tag = match tagged_file.primary_tag_mut() {
-            Some(primary_tag) => primary_tag,
-            None => {
-                if let Some(first_tag) = tagged_file.first_tag_mut() {
-                    first_tag
-                } else {
-                    let tag_type = tagged_file.primary_tag_type();
-
-                    eprintln!("WARN: No tags found, creating a new tag of type `{tag_type:?}`");
-                    tagged_file.insert_tag(Tag::new(tag_type));
-
-                    tagged_file.primary_tag_mut().unwrap()
-                }
+        for path in entries.flatten().map(|e| e.path()) {
+            let name = path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or_default();
+            if name.starts_with(&format!("{stem}.")) && name.ends_with(".lrc") {
+                let _ = fs::remove_file(&path);
             }
-        };
-        tag.insert_text(ItemKey::Lyrics, lyrics);
-        tag.save_to_path(&music_file, WriteOptions::default())
-            .expect("ERROR: Failed to write the tag!");
-
-        println!("INFO: Tag successfully updated!");
-        let _ = fs::remove_file(&lyrics_file);
+        }
+    }
+    if job.cover.enabled
+        && job.cover.folder_cover
+        && let Some((data, mime)) = art
+    {
+        cover::write_folder_cover(&folder, &data, &mime);
+    }
+    if job.archive {
+        archive::set_file(ArchiveTool::Music, &entry.key, &folder.to_string_lossy());
     }
+    Some(files)
 }
 
 fn finder_lyrics(directory: &str, filename: &str) -> Option<PathBuf> {
-    let elements = fs::read_dir(&directory).ok()?;
-    let mut thing = Some(PathBuf::new());
+    let elements = fs::read_dir(directory).ok()?;
+    let mut thing = None;
 
     for item in elements {
         let path = item.ok()?.path();
-        if path.is_file() {
-            if path.extension().and_then(|ext| ext.to_str()) == Some("lrc") {
-                if let Some(file) = path.file_name().and_then(|name| name.to_str()) {
-                    if file.contains(filename) {
-                        thing = Some(path);
-                    }
-                }
-            }
+        if path.is_file()
+            && path.extension().and_then(|ext| ext.to_str()) == Some("lrc")
+            && let Some(file) = path.file_name().and_then(|name| name.to_str())
+            && file.contains(filename)
+        {
+            thing = Some(path);
         }
     }
     thing
@@ -0,0 +1,252 @@
+use crate::ui::shares::config;
+use crate::ui::shares::cover;
+use crate::ui::shares::lyrics;
+use crate::ui::shares::musicbrainz::{self, Candidate, Choice, Pending, ReleaseInfo};
+use eframe::egui::{self, Color32};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Releases listed per recording.
+const RELEASES_SHOWN: usize = 3;
+const THUMB: f32 = 56.0;
+
+enum Thumb {
+    Loading,
+    Ready(egui::TextureHandle),
+    Missing,
+}
+
+/// Tracks MusicBrainz wasn't sure about, one at a time. Nothing is written
+/// until a match is applied.
+#[derive(Default)]
+pub struct MusicBrainzReview {
+    queue: Vec<Pending>,
+    // Picked match for the first track, with its editable fields.
+    picked: Option<Choice>,
+    title: String,
+    artist: String,
+    album: String,
+    thumbs: HashMap<String, Thumb>,
+    // Thumbnails downloaded in the background, turned into textures on the UI thread.
+    fetched: Arc<Mutex<HashMap<String, Option<Vec<u8>>>>>,
+}
+
+impl MusicBrainzReview {
+    /// Picks up newly queued tracks. Returns true when there is something to review.
+    pub fn poll(&mut self) -> bool {
+        self.queue.extend(musicbrainz::take_pending());
+        !self.queue.is_empty()
+    }
+
+    fn pick(&mut self, candidate: &Candidate, release: Option<&ReleaseInfo>) {
+        let choice = candidate.choice(release);
+        self.title = choice.title.clone();
+        self.artist = choice.artist.clone();
+        self.album = choice.album.clone().unwrap_or_default();
+        self.picked = Some(choice);
+    }
+
+    fn next(&mut self) {
+        self.queue.remove(0);
+        self.picked = None;
+        if self.queue.is_empty() {
+            self.thumbs.clear();
+        }
+    }
+
+    fn apply(&mut self) {
+        let Some(mut choice) = self.picked.take() else {
+            return;
+        };
+        choice.title = self.title.trim().to_string();
+        choice.artist = self.artist.trim().to_string();
+        choice.album = Some(self.album.trim().to_string()).filter(|a| !a.is_empty());
+        let file = self.queue[0].file.clone();
+        tokio::task::spawn(async move {
+            let (cover_settings, policy) = match config::load_config(&config::get_config_file_path())
+            {
+                Ok(c) => (c.music_dl.cover, c.music_dl.release_policy),
+                Err(_) => Default::default(),
+            };
+            // Lyrics are usually in by now, keep their frames.
+            lyrics::keep_frames(&file, || {
+                match musicbrainz::apply(&file, &choice, &policy) {
+                    Ok(_) => println!("musicbrainz_review: Tagged {file:?}"),
+                    Err(e) => println!("musicbrainz_review: Fail to tag {file:?} {e}"),
+                }
+                cover::process_file(&file, &cover_settings);
+            });
+        });
+        self.next();
+    }
+
+    fn thumb(&mut self, ui: &mut egui::Ui, release_id: &str) {
+        let size = egui::vec2(THUMB, THUMB);
+        if !self.thumbs.contains_key(release_id) {
+            self.thumbs.insert(release_id.to_string(), Thumb::Loading);
+            let fetched = self.fetched.clone();
+            let id = release_id.to_string();
+            tokio::task::spawn(async move {
+                let data = musicbrainz::thumbnail(&id);
+                if let Ok(mut fetched) = fetched.lock() {
+                    fetched.insert(id, data);
+                }
+            });
+        }
+        if let Some(Thumb::Loading) = self.thumbs.get(release_id) {
+            let data = self
+                .fetched
+                .lock()
+                .ok()
+                .and_then(|mut f| f.remove(release_id));
+            match data {
+                Some(Some(data)) => {
+                    let thumb = match image::load_from_memory(&data) {
+                        Ok(img) => {
+                            let rgba = img.to_rgba8();
+                            let color = egui::ColorImage::from_rgba_unmultiplied(
+                                [rgba.width() as usize, rgba.height() as usize],
+                                rgba.as_raw(),
+                            );
+                            Thumb::Ready(ui.ctx().load_texture(
+                                release_id,
+                                color,
+                                egui::TextureOptions::default(),
+                            ))
+                        }
+                        Err(_) => Thumb::Missing,
+                    };
+                    self.thumbs.insert(release_id.to_string(), thumb);
+                }
+                Some(None) => {
+                    self.thumbs.insert(release_id.to_string(), Thumb::Missing);
+                }
+                None => ui.ctx().request_repaint_after(Duration::from_millis(300)),
+            }
+        }
+        match self.thumbs.get(release_id) {
+            Some(Thumb::Ready(texture)) => {
+                ui.image((texture.id(), size));
+            }
+            Some(Thumb::Loading) => {
+                ui.add_sized(size, egui::Spinner::new());
+            }
+            _ => {
+                ui.add_sized(size, egui::Label::new("-"));
+            }
+        }
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        if self.queue.is_empty() {
+            ui.label("Nothing to review");
+            return;
+        }
+        let pending = self.queue[0].clone();
+        ui.label(format!("{} track(s) to review", self.queue.len()));
+        ui.label(
+            pending
+                .file
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+        );
+        ui.small(format!("Searched: {} - {}", pending.title, pending.artist));
+        ui.separator();
+
+        egui::ScrollArea::vertical()
+            .max_height(360.0)
+            .show(ui, |ui| {
+                egui::Grid::new("musicbrainz_review_grid")
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for head in [
+                            "", "Score", "Title", "Artist", "Album", "Type", "Date", "Country",
+                        ] {
+                            ui.strong(head);
+                        }
+                        ui.end_row();
+                        for candidate in pending.candidates.iter() {
+                            let releases: Vec<Option<&ReleaseInfo>> =
+                                if candidate.releases.is_empty() {
+                                    vec![None]
+                                } else {
+                                    candidate
+                                        .releases
+                                        .iter()
+                                        .take(RELEASES_SHOWN)
+                                        .map(Some)
+                                        .collect()
+                                };
+                            for release in releases {
+                                match release {
+                                    Some(r) => self.thumb(ui, &r.id),
+                                    None => {
+                                        ui.label("");
+                                    }
+                                }
+                                let color = if candidate.score >= 90 {
+                                    Color32::LIGHT_GREEN
+                                } else if candidate.score >= 70 {
+                                    Color32::YELLOW
+                                } else {
+                                    Color32::LIGHT_RED
+                                };
+                                ui.colored_label(color, candidate.score.to_string());
+                                ui.label(&candidate.title);
+                                ui.label(&candidate.artist);
+                                ui.label(release.map(|r| r.title.as_str()).unwrap_or("-"));
+                                ui.label(
+                                    release
+                                        .map(|r| r.kind.as_str())
+                                        .filter(|k| !k.is_empty())
+                                        .unwrap_or("-"),
+                                );
+                                ui.label(release.and_then(|r| r.date.as_deref()).unwrap_or("-"));
+                                ui.label(release.and_then(|r| r.country.as_deref()).unwrap_or("-"));
+                                let selected = self.picked.as_ref().is_some_and(|p| {
+                                    p.recording_id == candidate.recording_id
+                                        && p.release_id.as_deref() == release.map(|r| r.id.as_str())
+                                });
+                                if ui.selectable_label(selected, "Pick").clicked() {
+                                    self.pick(candidate, release);
+                                }
+                                ui.end_row();
+                            }
+                        }
+                    });
+            });
+        ui.separator();
+
+        ui.add_enabled_ui(self.picked.is_some(), |ui| {
+            egui::Grid::new("musicbrainz_edit_grid").show(ui, |ui| {
+                ui.label("Title: ");
+                ui.text_edit_singleline(&mut self.title);
+                ui.end_row();
+                ui.label("Artist: ");
+                ui.text_edit_singleline(&mut self.artist);
+                ui.end_row();
+                ui.label("Album: ");
+                ui.text_edit_singleline(&mut self.album);
+                ui.end_row();
+            });
+        });
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(self.picked.is_some(), egui::Button::new("Apply"))
+                .clicked()
+            {
+                self.apply();
+            }
+            if ui.button("Skip").clicked() {
+                self.next();
+            }
+            if ui.button("Skip all").clicked() {
+                self.queue.clear();
+                self.picked = None;
+                self.thumbs.clear();
+            }
+        });
+    }
+}
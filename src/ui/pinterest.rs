@@ -8,9 +8,9 @@ use std::path::Path;
 use std::process::Command;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
-use tokio;
-use ureq::get;
 
+use crate::ui::shares::cookies;
+use crate::ui::shares::network;
 use crate::ui::shares::notify::{button_sound, done_sound, notification_done};
 
 pub struct PinterstDownload {
@@ -108,7 +108,11 @@ impl PinterstDownload {
 
 fn download(link: String, directory: String, videoorimg: bool) {
     if videoorimg {
-        let output = Command::new("yt-dlp")
+        let mut yt = Command::new("yt-dlp");
+        let net = network::settings();
+        network::apply_yt_dlp(&mut yt, &net);
+        cookies::apply(&mut yt, &link, &net);
+        let output = yt
             .arg(&link)
             .current_dir(&directory)
             .output()
@@ -122,10 +126,8 @@ fn download(link: String, directory: String, videoorimg: bool) {
     }
 }
 
-fn pin_pic_dl(link: &String, directory: &String) -> Result<(), Box<dyn Error>> {
-    let body = ureq::get(link)
-        .header("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/136.0.0.0 Safari/537.36")
-        .call()?
+fn pin_pic_dl(link: &str, directory: &str) -> Result<(), Box<dyn Error>> {
+    let body = network::get_with(link, &[("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/136.0.0.0 Safari/537.36")])?
         .body_mut()
         .read_to_string()?;
     let doc = Html::parse_document(&body);
@@ -136,7 +138,7 @@ fn pin_pic_dl(link: &String, directory: &String) -> Result<(), Box<dyn Error>> {
             println!("First image URL: {}", src);
             let filename = src.split("/").last().unwrap();
 
-            let response = get(src).call().expect("Failed to download image");
+            let response = network::get(src).expect("Failed to download image");
 
             let (_, body) = response.into_parts();
 
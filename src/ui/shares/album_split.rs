@@ -0,0 +1,279 @@
+use lofty::config::WriteOptions;
+use lofty::picture::Picture;
+use lofty::prelude::*;
+use lofty::probe::Probe;
+use lofty::tag::Tag;
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+#[derive(Debug, Clone)]
+pub struct Track {
+    pub start: f64,
+    pub end: Option<f64>,
+    pub title: String,
+}
+
+/// "1:02:03", "02:03" or "2:03.5" into seconds.
+pub fn parse_timestamp(s: &str) -> Option<f64> {
+    let parts: Vec<&str> = s.split(':').collect();
+    if parts.len() < 2 || parts.len() > 3 {
+        return None;
+    }
+    let mut seconds = 0.0;
+    for part in parts.iter() {
+        if part.is_empty() || !part.chars().all(|c| c.is_ascii_digit() || c == '.') {
+            return None;
+        }
+        seconds = seconds * 60.0 + part.parse::<f64>().ok()?;
+    }
+    Some(seconds)
+}
+
+/// Reads a tracklist pasted from a video description. Each line needs a timestamp
+/// somewhere ("0:00 Intro", "1. Song - 03:25", "[12:34] Title"); the rest is the title.
+pub fn parse_tracklist(text: &str) -> Vec<Track> {
+    let mut tracks: Vec<Track> = Vec::new();
+    for line in text.lines() {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let found = words.iter().enumerate().find_map(|(i, w)| {
+            let w = w.trim_matches(|c: char| "[]()<>|-–".contains(c));
+            parse_timestamp(w).map(|t| (i, t))
+        });
+        if let Some((i, start)) = found {
+            let rest: Vec<&str> = words
+                .iter()
+                .enumerate()
+                .filter(|(j, _)| *j != i)
+                .map(|(_, w)| *w)
+                .collect();
+            let joined = rest.join(" ");
+            let mut title = joined.trim_matches(|c: char| c.is_whitespace() || "-–—|:".contains(c));
+            // Drop a leading "1." or "01)" track number.
+            let digits = title.chars().take_while(|c| c.is_ascii_digit()).count();
+            if digits > 0 && matches!(title[digits..].chars().next(), Some('.') | Some(')')) {
+                title = title[digits + 1..]
+                    .trim_start_matches(|c: char| c.is_whitespace() || "-–—|:".contains(c));
+            }
+            let title = title.trim().to_string();
+            tracks.push(Track {
+                start,
+                end: None,
+                title,
+            });
+        }
+    }
+    tracks.sort_by(|a, b| a.start.total_cmp(&b.start));
+    for i in 0..tracks.len() {
+        tracks[i].end = tracks.get(i + 1).map(|t| t.start);
+        if tracks[i].title.is_empty() {
+            tracks[i].title = format!("Track {}", i + 1);
+        }
+    }
+    tracks
+}
+
+pub fn chapters_from_info(info: &Value) -> Vec<Track> {
+    info.get("chapters")
+        .and_then(|c| c.as_array())
+        .map(|chapters| {
+            chapters
+                .iter()
+                .enumerate()
+                .map(|(i, c)| Track {
+                    start: c.get("start_time").and_then(|v| v.as_f64()).unwrap_or(0.0),
+                    end: c.get("end_time").and_then(|v| v.as_f64()),
+                    title: c
+                        .get("title")
+                        .and_then(|v| v.as_str())
+                        .map(|s| s.to_string())
+                        .unwrap_or_else(|| format!("Track {}", i + 1)),
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn safe_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if "/\\:*?\"<>|".contains(c) { '_' } else { c })
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+/// Cuts `file` into one file per track inside a folder named after the album, tags each
+/// with title, album, artist, track number/total and the album's cover, then removes the
+/// original. Returns the folder and the track files.
+pub fn split(file: &Path, tracks: &[Track], album: &str) -> Option<(PathBuf, Vec<PathBuf>)> {
+    let ext = file.extension()?.to_str()?;
+    let folder = file.parent()?.join(safe_name(album));
+    let _ = fs::create_dir_all(&folder);
+
+    let (artist, cover) = match Probe::open(file).and_then(|p| p.read()) {
+        Ok(tagged_file) => {
+            let tag = tagged_file
+                .primary_tag()
+                .or_else(|| tagged_file.first_tag());
+            (
+                tag.and_then(|t| t.artist().map(|a| a.to_string())),
+                tag.and_then(|t| t.pictures().first().cloned()),
+            )
+        }
+        Err(e) => {
+            println!("album_split: Fail to read source tags {e}");
+            (None, None)
+        }
+    };
+
+    let total = tracks.len() as u32;
+    let mut out = Vec::new();
+    for (i, track) in tracks.iter().enumerate() {
+        let number = i as u32 + 1;
+        let target = folder.join(format!(
+            "{:02} - {}.{}",
+            number,
+            safe_name(&track.title),
+            ext
+        ));
+        let mut ffmpeg = Command::new("ffmpeg");
+        ffmpeg
+            .arg("-y")
+            .arg("-i")
+            .arg(file)
+            .arg("-ss")
+            .arg(format!("{:.3}", track.start));
+        if let Some(end) = track.end {
+            ffmpeg.arg("-to").arg(format!("{:.3}", end));
+        }
+        let output = ffmpeg
+            .arg("-map")
+            .arg("0:a")
+            .arg("-map_metadata")
+            .arg("-1")
+            .arg("-c")
+            .arg("copy")
+            .arg(&target)
+            .output();
+        match output {
+            Ok(o) if o.status.success() => {}
+            Ok(o) => {
+                println!(
+                    "album_split: ffmpeg fail on {}\n{}",
+                    track.title,
+                    String::from_utf8_lossy(&o.stderr)
+                );
+                continue;
+            }
+            Err(e) => {
+                println!("album_split: Fail to run ffmpeg {e}");
+                return None;
+            }
+        }
+        tag_track(
+            &target,
+            track,
+            album,
+            artist.as_deref(),
+            number,
+            total,
+            cover.clone(),
+        );
+        out.push(target);
+    }
+    if out.is_empty() {
+        return None;
+    }
+    let _ = fs::remove_file(file);
+    Some((folder, out))
+}
+
+fn tag_track(
+    path: &Path,
+    track: &Track,
+    album: &str,
+    artist: Option<&str>,
+    number: u32,
+    total: u32,
+    cover: Option<Picture>,
+) {
+    let mut tagged_file = match Probe::open(path).and_then(|p| p.read()) {
+        Ok(f) => f,
+        Err(e) => {
+            println!("album_split: Fail to open {path:?} {e}");
+            return;
+        }
+    };
+    let tag_type = tagged_file.primary_tag_type();
+    if tagged_file.primary_tag().is_none() {
+        tagged_file.insert_tag(Tag::new(tag_type));
+    }
+    let tag = tagged_file.primary_tag_mut().unwrap();
+    tag.set_title(track.title.clone());
+    tag.set_album(album.to_string());
+    if let Some(artist) = artist {
+        tag.set_artist(artist.to_string());
+        tag.insert_text(ItemKey::AlbumArtist, artist.to_string());
+    }
+    tag.set_track(number);
+    tag.set_track_total(total);
+    if let Some(picture) = cover {
+        tag.push_picture(picture);
+    }
+    if let Err(e) = tag.save_to_path(path, WriteOptions::default()) {
+        println!("album_split: Fail to tag {path:?} {e}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timestamps() {
+        assert_eq!(parse_timestamp("2:03"), Some(123.0));
+        assert_eq!(parse_timestamp("02:03.5"), Some(123.5));
+        assert_eq!(parse_timestamp("1:02:03"), Some(3723.0));
+        assert_eq!(parse_timestamp("123"), None);
+        assert_eq!(parse_timestamp("1:2:3:4"), None);
+        assert_eq!(parse_timestamp("1:ab"), None);
+        assert_eq!(parse_timestamp(":30"), None);
+    }
+
+    #[test]
+    fn tracklist_formats() {
+        let tracks =
+            parse_tracklist("0:00 Intro\n1. Second Song - 03:25\n[12:34] Third\nno time here\n");
+        let starts: Vec<f64> = tracks.iter().map(|t| t.start).collect();
+        let titles: Vec<&str> = tracks.iter().map(|t| t.title.as_str()).collect();
+        assert_eq!(starts, [0.0, 205.0, 754.0]);
+        assert_eq!(titles, ["Intro", "Second Song", "Third"]);
+    }
+
+    #[test]
+    fn tracklist_sorted_with_ends() {
+        let tracks = parse_tracklist("4:00 Last\n0:00 First\n2:00");
+        let ends: Vec<Option<f64>> = tracks.iter().map(|t| t.end).collect();
+        assert_eq!(tracks[0].title, "First");
+        assert_eq!(tracks[1].title, "Track 2");
+        assert_eq!(ends, [Some(120.0), Some(240.0), None]);
+    }
+
+    #[test]
+    fn chapters() {
+        let info: Value = serde_json::from_str(
+            r#"{"chapters": [
+                {"start_time": 0.0, "end_time": 60.0, "title": "One"},
+                {"start_time": 60.0, "end_time": 130.5}
+            ]}"#,
+        )
+        .unwrap();
+        let tracks = chapters_from_info(&info);
+        assert_eq!(tracks.len(), 2);
+        assert_eq!(tracks[0].title, "One");
+        assert_eq!(tracks[1].title, "Track 2");
+        assert_eq!(tracks[1].end, Some(130.5));
+        assert!(chapters_from_info(&Value::Null).is_empty());
+    }
+}
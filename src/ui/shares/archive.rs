@@ -0,0 +1,282 @@
+use crate::ui::shares::config::get_config_dir_path;
+use eframe::egui;
+use native_dialog::DialogBuilder;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// One archive per yt-dlp tool: the `.txt` file is handed to yt-dlp with
+// `--download-archive`, the `.toml` file is our own record of where the
+// post-processed file ended up.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ArchiveTool {
+    Music,
+    Video,
+}
+
+impl ArchiveTool {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ArchiveTool::Music => "music",
+            ArchiveTool::Video => "video",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveEntry {
+    pub key: String,
+    pub title: String,
+    pub file: String,
+    pub date: u64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ArchiveRecord {
+    entries: Vec<ArchiveEntry>,
+}
+
+fn archive_dir() -> PathBuf {
+    let dir = get_config_dir_path().join("archive");
+    if !dir.exists() {
+        let _ = fs::create_dir_all(&dir);
+    }
+    dir
+}
+pub fn archive_file(tool: ArchiveTool) -> PathBuf {
+    archive_dir().join(format!("{}.txt", tool.name()))
+}
+fn record_file(tool: ArchiveTool) -> PathBuf {
+    archive_dir().join(format!("{}.toml", tool.name()))
+}
+fn output_file(tool: ArchiveTool) -> PathBuf {
+    let stamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    archive_dir().join(format!("{}-{stamp}.moved", tool.name()))
+}
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Makes yt-dlp write every finished file (after post-processing) to a
+/// scratch file, and skip entries already in the archive when `archive` is on.
+/// The returned path is what `collect` reads back.
+pub fn apply(yt: &mut Command, tool: ArchiveTool, archive: bool) -> PathBuf {
+    let moved = output_file(tool);
+    if archive {
+        yt.arg("--download-archive").arg(archive_file(tool));
+    }
+    yt.arg("--print-to-file")
+        .arg("after_move:%(extractor_key)s %(id)s\t%(title)s\t%(filepath)s")
+        .arg(&moved);
+    moved
+}
+
+// yt-dlp writes archive lines as "youtube dQw4w9WgXcQ": the extractor in
+// lowercase, the id exactly as the site has it.
+fn archive_key(raw: &str) -> Option<String> {
+    let (extractor, id) = raw.trim().split_once(' ')?;
+    Some(format!("{} {id}", extractor.to_lowercase()))
+}
+
+/// Reads the files finished by the last yt-dlp run and adds them to the record.
+pub fn collect(tool: ArchiveTool, moved: &Path, archive: bool) -> Vec<ArchiveEntry> {
+    let content = fs::read_to_string(moved).unwrap_or_default();
+    let _ = fs::remove_file(moved);
+    let entries: Vec<ArchiveEntry> = content
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.splitn(3, '\t');
+            let key = archive_key(parts.next()?)?;
+            let title = parts.next()?.to_string();
+            let file = parts.next()?.to_string();
+            Some(ArchiveEntry {
+                key,
+                title,
+                file,
+                date: now(),
+            })
+        })
+        .collect();
+    if archive && !entries.is_empty() {
+        let mut record = load_record(tool);
+        for entry in entries.iter() {
+            record.entries.retain(|e| e.key != entry.key);
+            record.entries.push(entry.clone());
+        }
+        save_record(tool, &record);
+    }
+    entries
+}
+
+pub fn entries(tool: ArchiveTool) -> Vec<ArchiveEntry> {
+    load_record(tool).entries
+}
+
+fn load_record(tool: ArchiveTool) -> ArchiveRecord {
+    match fs::read_to_string(record_file(tool)) {
+        Ok(s) => toml::from_str(&s).unwrap_or_else(|e| {
+            eprintln!("archive: Fail to read {} record {e}", tool.name());
+            ArchiveRecord::default()
+        }),
+        Err(_) => ArchiveRecord::default(),
+    }
+}
+fn save_record(tool: ArchiveTool, record: &ArchiveRecord) {
+    match toml::to_string(record) {
+        Ok(s) => {
+            if let Err(e) = fs::write(record_file(tool), s) {
+                eprintln!("archive: Fail to save {} record {e}", tool.name());
+            }
+        }
+        Err(e) => eprintln!("archive: Fail to serialize {} record {e}", tool.name()),
+    }
+}
+
+fn archive_lines(tool: ArchiveTool) -> Vec<String> {
+    fs::read_to_string(archive_file(tool))
+        .unwrap_or_default()
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| l.to_string())
+        .collect()
+}
+fn save_archive_lines(tool: ArchiveTool, lines: &[String]) {
+    let mut content = lines.join("\n");
+    if !content.is_empty() {
+        content.push('\n');
+    }
+    if let Err(e) = fs::write(archive_file(tool), content) {
+        eprintln!("archive: Fail to save {} archive {e}", tool.name());
+    }
+}
+
+/// Points an entry at a different file, e.g. the folder an album was split into.
+pub fn set_file(tool: ArchiveTool, key: &str, file: &str) {
+    let mut record = load_record(tool);
+    for entry in record.entries.iter_mut().filter(|e| e.key == key) {
+        entry.file = file.to_string();
+    }
+    save_record(tool, &record);
+}
+
+/// Forgets the given keys, so yt-dlp downloads them again next time.
+pub fn remove(tool: ArchiveTool, keys: &[String]) {
+    let mut record = load_record(tool);
+    record.entries.retain(|e| !keys.contains(&e.key));
+    save_record(tool, &record);
+    let mut lines = archive_lines(tool);
+    lines.retain(|l| !keys.contains(l));
+    save_archive_lines(tool, &lines);
+}
+
+/// Drops every entry whose post-processed file is gone. Returns how many were removed.
+pub fn prune(tool: ArchiveTool) -> usize {
+    let gone: Vec<String> = load_record(tool)
+        .entries
+        .into_iter()
+        .filter(|e| !Path::new(&e.file).exists())
+        .map(|e| e.key)
+        .collect();
+    remove(tool, &gone);
+    gone.len()
+}
+
+/// Merges a yt-dlp archive file into ours. Returns how many new lines were added.
+pub fn import(tool: ArchiveTool, from: &Path) -> Result<usize, std::io::Error> {
+    let incoming = fs::read_to_string(from)?;
+    let mut lines = archive_lines(tool);
+    let mut added = 0;
+    for line in incoming.lines().map(|l| l.trim()).filter(|l| !l.is_empty()) {
+        if !lines.iter().any(|l| l == line) {
+            lines.push(line.to_string());
+            added += 1;
+        }
+    }
+    save_archive_lines(tool, &lines);
+    Ok(added)
+}
+
+pub fn export(tool: ArchiveTool, to: &Path) -> Result<(), std::io::Error> {
+    let mut lines = archive_lines(tool);
+    for entry in load_record(tool).entries {
+        if !lines.contains(&entry.key) {
+            lines.push(entry.key);
+        }
+    }
+    fs::write(to, lines.join("\n") + "\n")
+}
+
+pub fn archive_ui(ui: &mut egui::Ui, tool: ArchiveTool) {
+    let lines = archive_lines(tool);
+    let record = entries(tool);
+    ui.label(format!("{} archived", lines.len()));
+    egui::ScrollArea::vertical()
+        .max_height(250.0)
+        .show(ui, |ui| {
+            for entry in record.iter().rev() {
+                ui.horizontal(|ui| {
+                    if ui.small_button("x").on_hover_text("Remove").clicked() {
+                        remove(tool, std::slice::from_ref(&entry.key));
+                    }
+                    let exists = Path::new(&entry.file).exists();
+                    let label = ui.label(&entry.title);
+                    if exists {
+                        label.on_hover_text(&entry.file);
+                    } else {
+                        label.on_hover_text(format!("Missing: {}", entry.file));
+                    }
+                });
+            }
+        });
+    ui.separator();
+    ui.horizontal(|ui| {
+        if ui.button("Import").clicked() {
+            let path = DialogBuilder::file()
+                .add_filter("Archive", ["txt"])
+                .open_single_file()
+                .show()
+                .unwrap();
+            if let Some(p) = path {
+                match import(tool, &p) {
+                    Ok(n) => println!("archive: Imported {n} entries"),
+                    Err(e) => eprintln!("archive: Fail to import {e}"),
+                }
+            }
+        }
+        if ui.button("Export").clicked() {
+            let path = DialogBuilder::file()
+                .set_filename(format!("azulbox_{}_archive.txt", tool.name()))
+                .save_single_file()
+                .show()
+                .unwrap();
+            if let Some(p) = path
+                && let Err(e) = export(tool, &p)
+            {
+                eprintln!("archive: Fail to export {e}");
+            }
+        }
+        if ui
+            .button("Prune")
+            .on_hover_text("Forget entries whose file was deleted")
+            .clicked()
+        {
+            let n = prune(tool);
+            println!("archive: Pruned {n} entries");
+        }
+        if ui.button("Clear").clicked() {
+            let mut keys: Vec<String> = record.iter().map(|e| e.key.clone()).collect();
+            keys.extend(lines);
+            remove(tool, &keys);
+        }
+    });
+}
@@ -0,0 +1,322 @@
+use eframe::egui;
+use lofty::config::WriteOptions;
+use lofty::prelude::*;
+use lofty::probe::Probe;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AudioFormat {
+    Opus,
+    Flac,
+    Mp3,
+    /// AAC in an M4A container.
+    Aac,
+    Wav,
+    /// The source stream as is, without transcoding.
+    Original,
+    Alac,
+    Vorbis,
+    Aiff,
+    WavPack,
+}
+
+impl AudioFormat {
+    pub const ALL: [AudioFormat; 10] = [
+        AudioFormat::Opus,
+        AudioFormat::Flac,
+        AudioFormat::Mp3,
+        AudioFormat::Aac,
+        AudioFormat::Wav,
+        AudioFormat::Original,
+        AudioFormat::Alac,
+        AudioFormat::Vorbis,
+        AudioFormat::Aiff,
+        AudioFormat::WavPack,
+    ];
+
+    /// The numeric codes older configs stored.
+    pub fn from_code(code: i8) -> Self {
+        match code {
+            2 => AudioFormat::Flac,
+            3 => AudioFormat::Mp3,
+            4 => AudioFormat::Aac,
+            5 => AudioFormat::Wav,
+            _ => AudioFormat::Opus,
+        }
+    }
+    /// Name in the config, matching the serde names.
+    fn key(self) -> &'static str {
+        match self {
+            AudioFormat::Opus => "opus",
+            AudioFormat::Flac => "flac",
+            AudioFormat::Mp3 => "mp3",
+            AudioFormat::Aac => "aac",
+            AudioFormat::Wav => "wav",
+            AudioFormat::Original => "original",
+            AudioFormat::Alac => "alac",
+            AudioFormat::Vorbis => "vorbis",
+            AudioFormat::Aiff => "aiff",
+            AudioFormat::WavPack => "wavpack",
+        }
+    }
+    pub fn name(self) -> &'static str {
+        match self {
+            AudioFormat::Opus => "OPUS",
+            AudioFormat::Flac => "FLAC",
+            AudioFormat::Mp3 => "MP3",
+            AudioFormat::Aac => "AAC (M4A)",
+            AudioFormat::Wav => "WAV",
+            AudioFormat::Original => "Best / original",
+            AudioFormat::Alac => "ALAC",
+            AudioFormat::Vorbis => "Vorbis",
+            AudioFormat::Aiff => "AIFF",
+            AudioFormat::WavPack => "WavPack",
+        }
+    }
+    /// yt-dlp `--audio-format`. AIFF and WavPack aren't supported there, so they
+    /// are extracted as FLAC and converted afterwards by `convert`.
+    pub fn yt_dlp(self) -> &'static str {
+        match self {
+            AudioFormat::Opus => "opus",
+            AudioFormat::Flac | AudioFormat::Aiff | AudioFormat::WavPack => "flac",
+            AudioFormat::Mp3 => "mp3",
+            AudioFormat::Aac => "m4a",
+            AudioFormat::Wav => "wav",
+            AudioFormat::Original => "best",
+            AudioFormat::Alac => "alac",
+            AudioFormat::Vorbis => "vorbis",
+        }
+    }
+    pub fn lossless(self) -> bool {
+        matches!(
+            self,
+            AudioFormat::Flac
+                | AudioFormat::Wav
+                | AudioFormat::Alac
+                | AudioFormat::Aiff
+                | AudioFormat::WavPack
+        )
+    }
+}
+
+// Accepts the format name or one of the old numeric codes.
+impl<'de> Deserialize<'de> for AudioFormat {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Code(i64),
+            Name(String),
+        }
+        match Raw::deserialize(deserializer)? {
+            Raw::Code(code) => Ok(AudioFormat::from_code(code as i8)),
+            Raw::Name(name) => {
+                let name = name.to_lowercase();
+                AudioFormat::ALL
+                    .into_iter()
+                    .find(|f| f.key() == name || (name == "m4a" && *f == AudioFormat::Aac))
+                    .ok_or_else(|| serde::de::Error::custom(format!("unknown audio format {name}")))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "lowercase")]
+pub enum Quality {
+    /// yt-dlp's best VBR setting.
+    Best,
+    Cbr {
+        kbps: u32,
+    },
+    /// 0 (best) to 9 (smallest).
+    Vbr {
+        level: u8,
+    },
+}
+
+impl Quality {
+    fn arg(self) -> String {
+        match self {
+            Quality::Best => "0".to_string(),
+            Quality::Cbr { kbps } => format!("{kbps}K"),
+            Quality::Vbr { level } => level.min(9).to_string(),
+        }
+    }
+}
+
+/// Quality for the lossy formats; lossless ones have nothing to choose.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Qualities {
+    pub opus: Quality,
+    pub mp3: Quality,
+    pub aac: Quality,
+    pub vorbis: Quality,
+}
+
+impl Default for Qualities {
+    fn default() -> Self {
+        Self {
+            opus: Quality::Best,
+            mp3: Quality::Best,
+            aac: Quality::Cbr { kbps: 256 },
+            vorbis: Quality::Best,
+        }
+    }
+}
+
+impl Qualities {
+    pub fn get_mut(&mut self, format: AudioFormat) -> Option<&mut Quality> {
+        match format {
+            AudioFormat::Opus => Some(&mut self.opus),
+            AudioFormat::Mp3 => Some(&mut self.mp3),
+            AudioFormat::Aac => Some(&mut self.aac),
+            AudioFormat::Vorbis => Some(&mut self.vorbis),
+            _ => None,
+        }
+    }
+    fn get(&self, format: AudioFormat) -> Quality {
+        match format {
+            AudioFormat::Opus => self.opus,
+            AudioFormat::Mp3 => self.mp3,
+            AudioFormat::Aac => self.aac,
+            AudioFormat::Vorbis => self.vorbis,
+            _ => Quality::Best,
+        }
+    }
+}
+
+pub fn apply(yt: &mut Command, format: AudioFormat, qualities: &Qualities) {
+    if format == AudioFormat::Original {
+        // No --audio-format: yt-dlp only remuxes the stream, Opus to .opus and AAC to .m4a.
+        yt.arg("-f").arg("bestaudio/best");
+        return;
+    }
+    yt.arg("--audio-format")
+        .arg(format.yt_dlp())
+        .arg("--audio-quality")
+        .arg(qualities.get(format).arg());
+}
+
+// PCM as deep as the FLAC, so a 24-bit source stays 24-bit.
+fn aiff_codec(file: &Path) -> &'static str {
+    let depth = Probe::open(file)
+        .and_then(|p| p.read())
+        .ok()
+        .and_then(|f| f.properties().bit_depth());
+    match depth {
+        Some(d) if d > 24 => "pcm_s32be",
+        Some(d) if d > 16 => "pcm_s24be",
+        _ => "pcm_s16be",
+    }
+}
+
+/// Turns the FLAC yt-dlp made into AIFF or WavPack, carrying the tags and cover
+/// over. Returns the new file, or the original one when nothing was converted.
+pub fn convert(file: &Path, format: AudioFormat) -> PathBuf {
+    let (ext, codec) = match format {
+        AudioFormat::Aiff => ("aiff", aiff_codec(file)),
+        AudioFormat::WavPack => ("wv", "wavpack"),
+        _ => return file.to_path_buf(),
+    };
+    let target = file.with_extension(ext);
+    let mut ffmpeg = Command::new("ffmpeg");
+    let output = ffmpeg
+        .arg("-y")
+        .arg("-i")
+        .arg(file)
+        .arg("-map")
+        .arg("0:a")
+        .arg("-c:a")
+        .arg(codec)
+        .arg(&target)
+        .output();
+    match output {
+        Ok(o) if o.status.success() => {}
+        Ok(o) => {
+            println!(
+                "audio_format: ffmpeg fail {}",
+                String::from_utf8_lossy(&o.stderr)
+            );
+            return file.to_path_buf();
+        }
+        Err(e) => {
+            println!("audio_format: Fail to run ffmpeg {e}");
+            return file.to_path_buf();
+        }
+    }
+    copy_tags(file, &target);
+    let _ = fs::remove_file(file);
+    target
+}
+
+fn copy_tags(from: &Path, to: &Path) {
+    let tag = match Probe::open(from).and_then(|p| p.read()) {
+        Ok(f) => f.primary_tag().or_else(|| f.first_tag()).cloned(),
+        Err(e) => {
+            println!("audio_format: Fail to read tags {e}");
+            None
+        }
+    };
+    let Some(mut tag) = tag else {
+        return;
+    };
+    let target_type = match Probe::open(to).and_then(|p| p.read()) {
+        Ok(f) => f.primary_tag_type(),
+        Err(e) => {
+            println!("audio_format: Fail to open {to:?} {e}");
+            return;
+        }
+    };
+    tag.re_map(target_type);
+    if let Err(e) = tag.save_to_path(to, WriteOptions::default()) {
+        println!("audio_format: Fail to write tags {e}");
+    }
+}
+
+/// Mode and value for one lossy format. Returns true when it changed.
+pub fn quality_ui(ui: &mut egui::Ui, quality: &mut Quality) -> bool {
+    let mut changed = false;
+    ui.horizontal(|ui| {
+        let mut mode = match quality {
+            Quality::Best => 0,
+            Quality::Cbr { .. } => 1,
+            Quality::Vbr { .. } => 2,
+        };
+        let before = mode;
+        ui.radio_value(&mut mode, 0, "Best");
+        ui.radio_value(&mut mode, 1, "CBR");
+        ui.radio_value(&mut mode, 2, "VBR");
+        if mode != before {
+            *quality = match mode {
+                1 => Quality::Cbr { kbps: 256 },
+                2 => Quality::Vbr { level: 2 },
+                _ => Quality::Best,
+            };
+            changed = true;
+        }
+        match quality {
+            Quality::Best => {}
+            Quality::Cbr { kbps } => {
+                changed |= ui
+                    .add(
+                        egui::Slider::new(kbps, 64..=320)
+                            .step_by(32.0)
+                            .suffix(" kbps"),
+                    )
+                    .changed();
+            }
+            Quality::Vbr { level } => {
+                changed |= ui
+                    .add(egui::Slider::new(level, 0..=9).text("(0 = best)"))
+                    .changed();
+            }
+        }
+    });
+    changed
+}
@@ -0,0 +1,353 @@
+use eframe::egui::{self, Color32};
+use lofty::config::WriteOptions;
+use lofty::prelude::*;
+use lofty::probe::Probe;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::{LazyLock, Mutex};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Cleanup {
+    pub enabled: bool,
+    /// Regexes whose matches are removed from the title.
+    pub remove: Vec<String>,
+    /// "Artist - Title" in the title becomes artist and title.
+    pub split: bool,
+    /// "ArtistVEVO", "Artist - Topic", "Artist Official" become "Artist".
+    pub strip_suffix: bool,
+    /// "(ft. X)" in the title moves to the artist as "feat. X".
+    pub feat: bool,
+}
+
+impl Default for Cleanup {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            remove: default_remove(),
+            split: true,
+            strip_suffix: true,
+            feat: true,
+        }
+    }
+}
+
+pub fn default_remove() -> Vec<String> {
+    [
+        r"(?i)\s*[(\[](official\s*)?(hd\s*|4k\s*)?(music\s*)?(video|audio|lyrics?(\s*video)?|visuali[sz]er|mv)[)\]]",
+        r"(?i)\s*[(\[](4k|8k|hd|hq|uhd|1080p|720p|remastered in 4k)[)\]]",
+        r"(?i)\s*[(\[]official[^)\]]*[)\]]",
+        r"(?i)\s*[(\[](explicit|clean)[)\]]",
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect()
+}
+
+static SUFFIX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)(\s*-\s*topic|vevo|\s+official(\s+channel)?)$").unwrap());
+// "(feat. X)" up to its closing bracket, or a bare "ft. X" up to the next
+// bracket or separator so "Song ft. X (Remix)" keeps its "(Remix)".
+static FEAT: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?i)\s*(?:[(\[]\s*(?:feat\.?|ft\.?|featuring)\s+([^)\]]+?)\s*[)\]]|\b(?:feat\.?|ft\.?|featuring)\s+([^(\[]+?)(?:\s*$|\s*[(\[]|\s+[-–—|]\s))",
+    )
+    .unwrap()
+});
+static FEAT_WORD: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)\s+(?:ft\.?|feat\.?|featuring)\s+").unwrap());
+static SPACES: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\s{2,}").unwrap());
+// "- Remastered 2011", "- Live at ..." are part of the title, not an artist split.
+static VERSION: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)^(\d{4}\s+)?(remaster|live|remix|version|edit|mix|acoustic|demo|mono|stereo|radio|extended|instrumental|bonus)")
+        .unwrap()
+});
+
+// The last set of removal rules, compiled. Replaced when the rules change, so
+// it never holds more than one set. None for a bad pattern.
+type Compiled = (Vec<String>, Vec<Option<Regex>>);
+static COMPILED: LazyLock<Mutex<Compiled>> = LazyLock::new(|| Mutex::new((Vec::new(), Vec::new())));
+
+fn compiled(patterns: &[String]) -> Vec<Option<Regex>> {
+    let mut compiled = COMPILED.lock().unwrap_or_else(|e| e.into_inner());
+    if compiled.0 != patterns {
+        *compiled = (
+            patterns.to_vec(),
+            patterns.iter().map(|p| Regex::new(p).ok()).collect(),
+        );
+    }
+    compiled.1.clone()
+}
+
+fn tidy(s: &str) -> String {
+    SPACES
+        .replace_all(s, " ")
+        .trim_matches(|c: char| c.is_whitespace() || c == '-' || c == '|')
+        .to_string()
+}
+
+/// Returns (title, artist) after the pipeline: suffix stripping, regex removal,
+/// artist/title split, then feat. normalization.
+pub fn clean(c: &Cleanup, title: &str, artist: &str) -> (String, String) {
+    let mut title = title.to_string();
+    let mut artist = artist.to_string();
+    if !c.enabled {
+        return (title, artist);
+    }
+    if c.strip_suffix {
+        artist = SUFFIX.replace(&artist, "").to_string();
+    }
+    for re in compiled(&c.remove).into_iter().flatten() {
+        title = re.replace_all(&title, "").to_string();
+    }
+    if c.split {
+        let sep = [" - ", " – ", " — "].iter().find_map(|sep| {
+            title
+                .split_once(sep)
+                .map(|(a, t)| (a.to_string(), t.to_string()))
+        });
+        if let Some((left, right)) = sep
+            && !VERSION.is_match(right.trim())
+        {
+            // The uploader may be a label, so the artist in the title wins
+            // unless the artist field already holds it with more detail.
+            if !artist.to_lowercase().contains(&left.trim().to_lowercase()) {
+                artist = left.trim().to_string();
+            }
+            title = right;
+        }
+    }
+    if c.feat {
+        artist = FEAT_WORD.replace_all(&artist, " feat. ").to_string();
+        if let Some(caps) = FEAT.captures(&title) {
+            let whole = caps.get(0).map_or(0..0, |m| m.range());
+            // A bare feat. ends where its guest does, the bracket or separator stays.
+            let (guest, end) = match (caps.get(1), caps.get(2)) {
+                (Some(g), _) => (g.as_str().trim().to_string(), whole.end),
+                (None, Some(g)) => (g.as_str().trim().to_string(), g.end()),
+                (None, None) => (String::new(), whole.end),
+            };
+            title.replace_range(whole.start..end, "");
+            if !artist.to_lowercase().contains(&guest.to_lowercase()) {
+                artist = if artist.trim().is_empty() {
+                    guest
+                } else {
+                    format!("{} feat. {guest}", artist.trim())
+                };
+            }
+        }
+    }
+    (tidy(&title), tidy(&artist))
+}
+
+/// Rewrites the title and artist tags of a downloaded file, before any online lookup.
+pub fn tag_file(path: &Path, c: &Cleanup) {
+    if !c.enabled {
+        return;
+    }
+    for (pattern, re) in c.remove.iter().zip(compiled(&c.remove)) {
+        if re.is_none() {
+            println!("cleanup: Bad regex {pattern}");
+        }
+    }
+    let mut tagged_file = match Probe::open(path).and_then(|p| p.read()) {
+        Ok(f) => f,
+        Err(e) => {
+            println!("cleanup: Fail to open {path:?} {e}");
+            return;
+        }
+    };
+    let Some(tag) = tagged_file.primary_tag_mut() else {
+        return;
+    };
+    let title = tag.title().map(|t| t.to_string()).unwrap_or_default();
+    let artist = tag.artist().map(|a| a.to_string()).unwrap_or_default();
+    let (new_title, new_artist) = clean(c, &title, &artist);
+    if new_title == title && new_artist == artist {
+        return;
+    }
+    println!("cleanup: \"{artist}\" / \"{title}\" -> \"{new_artist}\" / \"{new_title}\"");
+    if !new_title.is_empty() {
+        tag.set_title(new_title);
+    }
+    if !new_artist.is_empty() {
+        tag.set_artist(new_artist);
+    }
+    if let Err(e) = tag.save_to_path(path, WriteOptions::default()) {
+        println!("cleanup: Fail to save {path:?} {e}");
+    }
+}
+
+pub struct Preview {
+    pub title: String,
+    pub artist: String,
+}
+
+impl Default for Preview {
+    fn default() -> Self {
+        Self {
+            title: "Artist - Song (Official Music Video) [4K] ft. Guest".to_string(),
+            artist: "ArtistVEVO".to_string(),
+        }
+    }
+}
+
+/// Pipeline settings with a live preview. Returns true when Save was pressed.
+pub fn cleanup_menu(ui: &mut egui::Ui, c: &mut Cleanup, preview: &mut Preview) -> bool {
+    ui.checkbox(&mut c.enabled, "Clean up titles and artists");
+    ui.checkbox(&mut c.strip_suffix, "Strip VEVO / Topic / Official");
+    ui.checkbox(&mut c.split, "Split \"Artist - Title\"");
+    ui.checkbox(&mut c.feat, "Move feat./ft. to artist");
+    ui.label("Remove from title (regex):");
+    let mut remove = None;
+    for (i, pattern) in c.remove.iter_mut().enumerate() {
+        ui.horizontal(|ui| {
+            if ui.small_button("x").on_hover_text("Remove").clicked() {
+                remove = Some(i);
+            }
+            ui.add(egui::TextEdit::singleline(pattern).desired_width(320.0));
+            if Regex::new(pattern).is_err() {
+                ui.colored_label(Color32::LIGHT_RED, "!");
+            }
+        });
+    }
+    if let Some(i) = remove {
+        c.remove.remove(i);
+    }
+    ui.horizontal(|ui| {
+        if ui.button("Add").clicked() {
+            c.remove.push(String::new());
+        }
+        if ui.button("Defaults").clicked() {
+            c.remove = default_remove();
+        }
+    });
+    ui.separator();
+    egui::Grid::new("cleanup_preview").show(ui, |ui| {
+        ui.label("Title: ");
+        ui.text_edit_singleline(&mut preview.title);
+        ui.end_row();
+        ui.label("Artist: ");
+        ui.text_edit_singleline(&mut preview.artist);
+        ui.end_row();
+        let (title, artist) = clean(c, &preview.title, &preview.artist);
+        ui.label("Becomes: ");
+        ui.colored_label(Color32::LIGHT_BLUE, format!("{artist} / {title}"));
+        ui.end_row();
+    });
+    let save = ui.button("Save").clicked();
+    if save {
+        ui.close_menu();
+    }
+    save
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(title: &str, artist: &str) -> (String, String) {
+        clean(&Cleanup::default(), title, artist)
+    }
+
+    #[test]
+    fn disabled_leaves_everything() {
+        let c = Cleanup {
+            enabled: false,
+            ..Cleanup::default()
+        };
+        assert_eq!(
+            clean(&c, "Artist - Song (Official Video)", "ArtistVEVO"),
+            (
+                "Artist - Song (Official Video)".to_string(),
+                "ArtistVEVO".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn strips_suffix_and_noise() {
+        assert_eq!(
+            run("Song (Official Music Video) [4K]", "ArtistVEVO"),
+            ("Song".to_string(), "Artist".to_string())
+        );
+        assert_eq!(
+            run("Song", "Artist - Topic"),
+            ("Song".to_string(), "Artist".to_string())
+        );
+    }
+
+    #[test]
+    fn splits_artist_from_title() {
+        assert_eq!(
+            run("Artist - Song", "Some Label"),
+            ("Song".to_string(), "Artist".to_string())
+        );
+        // The uploader already names the artist in more detail.
+        assert_eq!(
+            run("Artist - Song", "Artist & Band"),
+            ("Song".to_string(), "Artist & Band".to_string())
+        );
+    }
+
+    #[test]
+    fn keeps_version_suffix() {
+        assert_eq!(
+            run("Song - Remastered 2011", "Artist"),
+            ("Song - Remastered 2011".to_string(), "Artist".to_string())
+        );
+    }
+
+    #[test]
+    fn moves_feat_to_artist() {
+        assert_eq!(
+            run("Song (feat. Guest)", "Artist"),
+            ("Song".to_string(), "Artist feat. Guest".to_string())
+        );
+        assert_eq!(
+            run("Song ft. Guest", "Artist"),
+            ("Song".to_string(), "Artist feat. Guest".to_string())
+        );
+        assert_eq!(
+            run("Song", "Artist ft. Guest"),
+            ("Song".to_string(), "Artist feat. Guest".to_string())
+        );
+    }
+
+    #[test]
+    fn feat_stops_at_bracket_or_separator() {
+        assert_eq!(
+            run("Song ft. Guest (Remix)", "Artist"),
+            ("Song (Remix)".to_string(), "Artist feat. Guest".to_string())
+        );
+        assert_eq!(
+            run("Song feat. Guest [Live]", "Artist"),
+            ("Song [Live]".to_string(), "Artist feat. Guest".to_string())
+        );
+        assert_eq!(
+            run("Song (feat. Guest) [Live]", "Artist"),
+            ("Song [Live]".to_string(), "Artist feat. Guest".to_string())
+        );
+    }
+
+    #[test]
+    fn feat_not_repeated() {
+        assert_eq!(
+            run("Song (feat. Guest)", "Artist feat. Guest"),
+            ("Song".to_string(), "Artist feat. Guest".to_string())
+        );
+    }
+
+    #[test]
+    fn bad_pattern_is_skipped() {
+        let c = Cleanup {
+            remove: vec!["(".to_string(), r"\s*\(Lyrics\)".to_string()],
+            ..Cleanup::default()
+        };
+        assert_eq!(
+            clean(&c, "Song (Lyrics)", "Artist"),
+            ("Song".to_string(), "Artist".to_string())
+        );
+    }
+}
@@ -1,3 +1,17 @@
+use crate::ui::shares::archive::ArchiveTool;
+use crate::ui::shares::audio_format::{AudioFormat, Qualities};
+use crate::ui::shares::cleanup::Cleanup;
+use crate::ui::shares::cookies::CookieProfile;
+use crate::ui::shares::cover::CoverSettings;
+use crate::ui::shares::live::LiveSettings;
+use crate::ui::shares::lyrics_providers::LyricsProviders;
+use crate::ui::shares::metadata::{self, MetaRule};
+use crate::ui::shares::musicbrainz::{self, ReleasePolicy};
+use crate::ui::shares::network::Network;
+use crate::ui::shares::sidecars::Sidecars;
+use crate::ui::shares::sponsorblock::{self, SponsorBlock};
+use crate::ui::shares::subtitles::SubtitleSettings;
+use crate::ui::shares::translate::TranslateSettings;
 use serde::{Deserialize, Serialize};
 use std::{
     fs,
@@ -26,10 +40,13 @@ pub fn config_file_default() {
     }
 }
 pub fn get_config_file_path() -> PathBuf {
-    let azul_conf = "AzulBox";
     let azul_conf_file = "config.toml";
+    get_config_dir_path().join(azul_conf_file)
+}
+pub fn get_config_dir_path() -> PathBuf {
+    let azul_conf = "AzulBox";
     let config_dir = dirs::config_dir().expect("Could not find config directory");
-    config_dir.join(azul_conf).join(azul_conf_file)
+    config_dir.join(azul_conf)
 }
 
 fn save_config(config: &Config, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
@@ -57,11 +74,19 @@ pub struct Config {
     pub universal: Universal,
     pub video_dl: VideoDl,
     pub music_dl: MusicDl,
+    #[serde(default)]
+    pub subscriptions: Subscriptions,
+    #[serde(default)]
+    pub network: Network,
+    #[serde(default)]
+    pub cookies: Vec<CookieProfile>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Universal {
     pub language: String,
+    #[serde(default = "sponsorblock::default_api")]
+    pub sponsorblock_api: String,
 }
 #[derive(Debug, Serialize, Deserialize)]
 pub struct VideoDl {
@@ -69,38 +94,125 @@ pub struct VideoDl {
     pub subtitle: bool,
     pub auto_gen_sub: bool,
     pub fragments: i8,
+    #[serde(default)]
+    pub archive: bool,
+    #[serde(default)]
+    pub sponsorblock: SponsorBlock,
+    #[serde(default)]
+    pub exact_cuts: bool,
+    #[serde(default)]
+    pub live: LiveSettings,
+    #[serde(default)]
+    pub sidecars: Sidecars,
+    #[serde(default)]
+    pub subtitles: SubtitleSettings,
 }
 #[derive(Debug, Serialize, Deserialize)]
 pub struct MusicDl {
-    pub format: i8,
+    /// Old configs store 1-5, read as OPUS/FLAC/MP3/AAC/WAV.
+    pub format: AudioFormat,
+    #[serde(default)]
+    pub qualities: Qualities,
     pub lyrics: bool,
     pub auto_gen_sub: bool,
+    /// Only read when `providers` is empty, see `LyricsProviders::or_legacy`.
     pub liblrc: bool,
+    /// Keep a `.lrc` file next to every song, embedded or not.
+    #[serde(default)]
+    pub lrc_sidecar: bool,
+    #[serde(default)]
+    pub providers: LyricsProviders,
+    #[serde(default)]
+    pub translate: TranslateSettings,
+    /// Retry songs without lyrics every this many hours, 0 for never.
+    #[serde(default)]
+    pub lyrics_retry_hours: u32,
     pub musicbrainz: bool,
     pub threshold: i8,
+    /// Ask before writing matches below `auto_accept` or ambiguous ones.
+    #[serde(default)]
+    pub musicbrainz_review: bool,
+    #[serde(default = "musicbrainz::default_auto_accept")]
+    pub auto_accept: i8,
+    #[serde(default)]
+    pub release_policy: ReleasePolicy,
     pub fragments: i8,
+    #[serde(default)]
+    pub archive: bool,
+    #[serde(default = "SponsorBlock::music_default")]
+    pub sponsorblock: SponsorBlock,
+    #[serde(default)]
+    pub exact_cuts: bool,
+    #[serde(default = "metadata::default_rules")]
+    pub metadata: Vec<MetaRule>,
+    #[serde(default)]
+    pub cleanup: Cleanup,
+    #[serde(default)]
+    pub cover: CoverSettings,
+}
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Subscriptions {
+    pub background: bool,
+    pub list: Vec<Subscription>,
+}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Subscription {
+    pub name: String,
+    pub url: String,
+    pub tool: ArchiveTool,
+    /// Video format code.
+    pub preset: i8,
+    pub audio: AudioFormat,
+    pub directory: String,
+    pub interval_hours: u32,
+    #[serde(default)]
+    pub last_check: u64,
 }
 impl Default for Config {
     fn default() -> Self {
         Self {
             universal: Universal {
                 language: "en".to_string(),
+                sponsorblock_api: sponsorblock::default_api(),
             },
             video_dl: VideoDl {
                 format: 1,
                 subtitle: true,
                 auto_gen_sub: false,
                 fragments: 1,
+                archive: false,
+                sponsorblock: SponsorBlock::default(),
+                exact_cuts: false,
+                live: LiveSettings::default(),
+                sidecars: Sidecars::default(),
+                subtitles: SubtitleSettings::default(),
             },
             music_dl: MusicDl {
-                format: 1,
+                format: AudioFormat::Opus,
+                qualities: Qualities::default(),
                 lyrics: true,
                 auto_gen_sub: false,
                 liblrc: false,
+                lrc_sidecar: false,
+                providers: LyricsProviders::default(),
+                translate: TranslateSettings::default(),
+                lyrics_retry_hours: 0,
                 musicbrainz: false,
                 threshold: 90,
+                musicbrainz_review: false,
+                auto_accept: musicbrainz::default_auto_accept(),
+                release_policy: ReleasePolicy::default(),
                 fragments: 1,
+                archive: false,
+                sponsorblock: SponsorBlock::music_default(),
+                exact_cuts: false,
+                metadata: metadata::default_rules(),
+                cleanup: Cleanup::default(),
+                cover: CoverSettings::default(),
             },
+            subscriptions: Subscriptions::default(),
+            network: Network::default(),
+            cookies: Vec::new(),
         }
     }
 }
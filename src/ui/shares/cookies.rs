@@ -0,0 +1,276 @@
+use crate::ui::shares::config::{self, get_config_dir_path};
+use crate::ui::shares::network::Network;
+use eframe::egui;
+use native_dialog::DialogBuilder;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const BROWSERS: [&str; 8] = [
+    "firefox", "chrome", "chromium", "brave", "edge", "opera", "vivaldi", "safari",
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum CookieSource {
+    File { file: String },
+    Browser { browser: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CookieProfile {
+    pub name: String,
+    pub domains: Vec<String>,
+    pub source: CookieSource,
+}
+
+impl CookieProfile {
+    fn matches(&self, host: &str) -> bool {
+        self.domains.iter().any(|d| {
+            let d = d.trim().trim_start_matches('.');
+            !d.is_empty() && (host == d || host.ends_with(&format!(".{d}")))
+        })
+    }
+}
+
+// Cookies are credentials: the folder is only readable by the user.
+fn cookies_dir() -> PathBuf {
+    let dir = get_config_dir_path().join("cookies");
+    if !dir.exists() {
+        let _ = fs::create_dir_all(&dir);
+    }
+    restrict(&dir, 0o700);
+    dir
+}
+
+#[cfg(unix)]
+fn restrict(path: &Path, mode: u32) {
+    use std::os::unix::fs::PermissionsExt;
+    if let Err(e) = fs::set_permissions(path, fs::Permissions::from_mode(mode)) {
+        eprintln!("cookies: Fail to restrict {path:?} {e}");
+    }
+}
+#[cfg(not(unix))]
+fn restrict(_path: &Path, _mode: u32) {}
+
+fn looks_like_netscape(content: &str) -> bool {
+    content.starts_with("# Netscape HTTP Cookie File")
+        || content.starts_with("# HTTP Cookie File")
+        || content
+            .lines()
+            .any(|l| !l.starts_with('#') && l.split('\t').count() == 7)
+}
+
+/// Copies a Netscape cookies.txt into the config directory with user-only permissions.
+pub fn import_file(name: &str, from: &Path) -> Result<PathBuf, String> {
+    let content = fs::read_to_string(from).map_err(|e| e.to_string())?;
+    if !looks_like_netscape(&content) {
+        return Err("Not a Netscape cookies.txt file".to_string());
+    }
+    let safe: String = name
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect();
+    // Names like "a b" and "a_b" sanitize alike, each profile still gets its own file.
+    let dir = cookies_dir();
+    let target = (1..)
+        .map(|n| match n {
+            1 => dir.join(format!("{safe}.txt")),
+            n => dir.join(format!("{safe}_{n}.txt")),
+        })
+        .find(|p| !p.exists())
+        .unwrap_or_else(|| dir.join(format!("{safe}.txt")));
+    fs::write(&target, content).map_err(|e| e.to_string())?;
+    restrict(&target, 0o600);
+    Ok(target)
+}
+
+pub fn profile_for(link: &str) -> Option<CookieProfile> {
+    let host = url::Url::parse(link).ok()?.host_str()?.to_lowercase();
+    config::load_config(&config::get_config_file_path())
+        .ok()?
+        .cookies
+        .into_iter()
+        .find(|p| p.matches(&host))
+}
+
+/// Passes the profile matching the link's domain to yt-dlp, or the global
+/// cookies.txt from the network settings when no profile matches.
+pub fn apply(yt: &mut Command, link: &str, net: &Network) {
+    match profile_for(link) {
+        Some(profile) => {
+            println!("cookies: Using profile {}", profile.name);
+            match profile.source {
+                CookieSource::File { file } => {
+                    yt.arg("--cookies").arg(file);
+                }
+                CookieSource::Browser { browser } => {
+                    yt.arg("--cookies-from-browser").arg(browser);
+                }
+            }
+        }
+        None => {
+            if !net.cookies.trim().is_empty() && Path::new(net.cookies.trim()).exists() {
+                yt.arg("--cookies").arg(net.cookies.trim());
+            }
+        }
+    }
+}
+
+/// yt-dlp's ways of saying the video needs a logged-in account.
+pub fn auth_required(log: &str) -> bool {
+    let log = log.to_lowercase();
+    [
+        "sign in to confirm",
+        "confirm your age",
+        "members-only",
+        "available to this channel's members",
+        "join this channel",
+        "private video",
+        "video is private",
+        "login required",
+        "requires authentication",
+        "use --cookies",
+        "--cookies-from-browser",
+        "account cookies",
+    ]
+    .iter()
+    .any(|p| log.contains(p))
+}
+
+pub struct CookieForm {
+    pub name: String,
+    pub domains: String,
+    pub browser: String,
+    pub error: String,
+    // Saved profiles, read from the config once instead of every frame.
+    profiles: Vec<CookieProfile>,
+}
+
+impl Default for CookieForm {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            domains: String::new(),
+            browser: BROWSERS[0].to_string(),
+            error: String::new(),
+            profiles: config::load_config(&config::get_config_file_path())
+                .map(|c| c.cookies)
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl CookieForm {
+    fn add(&mut self, profile: CookieProfile) {
+        self.profiles.push(profile);
+        save_profiles(self.profiles.clone());
+        self.name.clear();
+        self.domains.clear();
+        self.error.clear();
+    }
+}
+
+fn save_profiles(profiles: Vec<CookieProfile>) {
+    let path = config::get_config_file_path();
+    match config::modifier_config(&path, |cfg| cfg.cookies = profiles) {
+        Ok(_) => {
+            println!("cookies: Saved profiles")
+        }
+        Err(e) => {
+            eprintln!("cookies: Fail to save profiles {e}")
+        }
+    }
+}
+
+pub fn cookies_menu(ui: &mut egui::Ui, form: &mut CookieForm) {
+    let mut remove = None;
+    for (i, profile) in form.profiles.iter().enumerate() {
+        ui.horizontal(|ui| {
+            if ui.small_button("x").on_hover_text("Remove").clicked() {
+                remove = Some(i);
+            }
+            let source = match &profile.source {
+                CookieSource::File { .. } => "cookies.txt".to_string(),
+                CookieSource::Browser { browser } => browser.clone(),
+            };
+            ui.label(format!(
+                "{} ({}) - {}",
+                profile.name,
+                profile.domains.join(", "),
+                source
+            ));
+        });
+    }
+    if let Some(i) = remove {
+        let removed = form.profiles.remove(i);
+        if let CookieSource::File { file } = removed.source {
+            let _ = fs::remove_file(file);
+        }
+        save_profiles(form.profiles.clone());
+    }
+    ui.separator();
+    egui::Grid::new("cookies_grid").show(ui, |ui| {
+        ui.label("Name: ");
+        ui.text_edit_singleline(&mut form.name);
+        ui.end_row();
+        ui.label("Domains: ");
+        ui.add(egui::TextEdit::singleline(&mut form.domains).hint_text("youtube.com, patreon.com"));
+        ui.end_row();
+    });
+    let domains: Vec<String> = form
+        .domains
+        .split(',')
+        .map(|d| d.trim().to_lowercase())
+        .filter(|d| !d.is_empty())
+        .collect();
+    let ready = !form.name.trim().is_empty() && !domains.is_empty();
+    ui.horizontal(|ui| {
+        if ui
+            .add_enabled(ready, egui::Button::new("Import cookies.txt"))
+            .clicked()
+        {
+            let path = DialogBuilder::file()
+                .add_filter("Cookies", ["txt"])
+                .open_single_file()
+                .show()
+                .unwrap();
+            if let Some(p) = path {
+                match import_file(form.name.trim(), &p) {
+                    Ok(file) => form.add(CookieProfile {
+                        name: form.name.trim().to_string(),
+                        domains: domains.clone(),
+                        source: CookieSource::File {
+                            file: file.to_string_lossy().into_owned(),
+                        },
+                    }),
+                    Err(e) => form.error = e,
+                }
+            }
+        }
+        ui.menu_button(form.browser.clone(), |ui| {
+            for browser in BROWSERS {
+                if ui.button(browser).clicked() {
+                    form.browser = browser.to_string();
+                    ui.close_menu();
+                }
+            }
+        });
+        if ui
+            .add_enabled(ready, egui::Button::new("Use browser"))
+            .clicked()
+        {
+            form.add(CookieProfile {
+                name: form.name.trim().to_string(),
+                domains: domains.clone(),
+                source: CookieSource::Browser {
+                    browser: form.browser.clone(),
+                },
+            });
+        }
+    });
+    if !form.error.is_empty() {
+        ui.colored_label(egui::Color32::LIGHT_RED, &form.error);
+    }
+}
@@ -0,0 +1,226 @@
+use eframe::egui;
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::PngEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, GenericImageView, ImageFormat};
+use lofty::config::WriteOptions;
+use lofty::picture::{MimeType, Picture, PictureType};
+use lofty::prelude::*;
+use lofty::probe::Probe;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CoverFormat {
+    Jpeg,
+    Png,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CoverSettings {
+    pub enabled: bool,
+    /// Trims black bars, then center-crops to a square.
+    pub square: bool,
+    pub max_size: u32,
+    pub format: CoverFormat,
+    pub jpeg_quality: u8,
+    /// Also write cover.jpg / cover.png into album folders.
+    pub folder_cover: bool,
+}
+
+impl Default for CoverSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            square: true,
+            max_size: 1200,
+            format: CoverFormat::Jpeg,
+            jpeg_quality: 90,
+            folder_cover: false,
+        }
+    }
+}
+
+/// The MIME type from the image bytes, not from where they came from.
+pub fn mime_of(data: &[u8]) -> Option<MimeType> {
+    match image::guess_format(data).ok()? {
+        ImageFormat::Jpeg => Some(MimeType::Jpeg),
+        ImageFormat::Png => Some(MimeType::Png),
+        ImageFormat::Gif => Some(MimeType::Gif),
+        ImageFormat::Bmp => Some(MimeType::Bmp),
+        ImageFormat::Tiff => Some(MimeType::Tiff),
+        ImageFormat::WebP => Some(MimeType::Unknown("image/webp".to_string())),
+        _ => None,
+    }
+}
+
+// Rows or columns darker than this on average count as letterbox bars.
+const BAR_LUMA: f32 = 20.0;
+
+fn content_range(means: &[f32]) -> (u32, u32) {
+    let start = means.iter().position(|m| *m > BAR_LUMA).unwrap_or(0);
+    let end = means
+        .iter()
+        .rposition(|m| *m > BAR_LUMA)
+        .map(|e| e + 1)
+        .unwrap_or(means.len());
+    (start as u32, end.saturating_sub(start) as u32)
+}
+
+fn trim_bars(img: &DynamicImage) -> DynamicImage {
+    let luma = img.to_luma8();
+    let (w, h) = luma.dimensions();
+    let mut rows = vec![0f32; h as usize];
+    let mut cols = vec![0f32; w as usize];
+    for (x, y, p) in luma.enumerate_pixels() {
+        rows[y as usize] += p.0[0] as f32 / w as f32;
+        cols[x as usize] += p.0[0] as f32 / h as f32;
+    }
+    let (y, ch) = content_range(&rows);
+    let (x, cw) = content_range(&cols);
+    // A mostly dark picture is not letterboxed, leave it.
+    if cw < w / 3 || ch < h / 3 {
+        return img.clone();
+    }
+    img.crop_imm(x, y, cw, ch)
+}
+
+fn center_square(img: &DynamicImage) -> DynamicImage {
+    let (w, h) = img.dimensions();
+    let side = w.min(h);
+    img.crop_imm((w - side) / 2, (h - side) / 2, side, side)
+}
+
+/// Crops, resizes and re-encodes cover bytes. None when the image can't be read
+/// or already fits the settings.
+pub fn process(data: &[u8], settings: &CoverSettings) -> Option<(Vec<u8>, MimeType)> {
+    let source_mime = mime_of(data);
+    let mut img = match image::load_from_memory(data) {
+        Ok(img) => img,
+        Err(e) => {
+            println!("cover: Fail to decode {e}");
+            return None;
+        }
+    };
+    let target_mime = match settings.format {
+        CoverFormat::Jpeg => MimeType::Jpeg,
+        CoverFormat::Png => MimeType::Png,
+    };
+    let (w, h) = img.dimensions();
+    let max = settings.max_size.max(64);
+    if (!settings.square || w == h) && w.max(h) <= max && source_mime.as_ref() == Some(&target_mime)
+    {
+        return None;
+    }
+    if settings.square {
+        img = center_square(&trim_bars(&img));
+    }
+    if img.width().max(img.height()) > max {
+        img = img.resize(max, max, FilterType::Lanczos3);
+    }
+    let mut out = Vec::new();
+    let result = match settings.format {
+        CoverFormat::Jpeg => DynamicImage::ImageRgb8(img.to_rgb8()).write_with_encoder(
+            JpegEncoder::new_with_quality(&mut out, settings.jpeg_quality.clamp(1, 100)),
+        ),
+        CoverFormat::Png => img.write_with_encoder(PngEncoder::new(&mut out)),
+    };
+    match result {
+        Ok(_) => Some((out, target_mime)),
+        Err(e) => {
+            println!("cover: Fail to encode {e}");
+            None
+        }
+    }
+}
+
+/// Runs the front cover (or the first picture) of a file through `process`.
+/// Returns the final cover bytes and MIME type.
+pub fn process_file(path: &Path, settings: &CoverSettings) -> Option<(Vec<u8>, MimeType)> {
+    if !settings.enabled {
+        return None;
+    }
+    let mut tagged_file = match Probe::open(path).and_then(|p| p.read()) {
+        Ok(f) => f,
+        Err(e) => {
+            println!("cover: Fail to open {path:?} {e}");
+            return None;
+        }
+    };
+    let tag = tagged_file.primary_tag_mut()?;
+    let index = tag
+        .pictures()
+        .iter()
+        .position(|p| p.pic_type() == PictureType::CoverFront)
+        .or((tag.picture_count() > 0).then_some(0))?;
+    let picture = tag.pictures()[index].clone();
+    let Some((data, mime)) = process(picture.data(), settings) else {
+        let mime = mime_of(picture.data())?;
+        return Some((picture.data().to_vec(), mime));
+    };
+    tag.remove_picture(index);
+    tag.push_picture(Picture::new_unchecked(
+        PictureType::CoverFront,
+        Some(mime.clone()),
+        None,
+        data.clone(),
+    ));
+    if let Err(e) = tag.save_to_path(path, WriteOptions::default()) {
+        println!("cover: Fail to save {path:?} {e}");
+    }
+    Some((data, mime))
+}
+
+pub fn write_folder_cover(folder: &Path, data: &[u8], mime: &MimeType) {
+    let name = if *mime == MimeType::Png {
+        "cover.png"
+    } else {
+        "cover.jpg"
+    };
+    if let Err(e) = fs::write(folder.join(name), data) {
+        println!("cover: Fail to write {name} {e}");
+    }
+}
+
+/// Returns true when a setting changed.
+pub fn cover_menu(ui: &mut egui::Ui, settings: &mut CoverSettings) -> bool {
+    let mut changed = ui
+        .checkbox(&mut settings.enabled, "Process cover art")
+        .changed();
+    ui.add_enabled_ui(settings.enabled, |ui| {
+        changed |= ui
+            .checkbox(&mut settings.square, "Crop to square")
+            .on_hover_text("Removes the black bars of video thumbnails")
+            .changed();
+        changed |= ui
+            .add(
+                egui::Slider::new(&mut settings.max_size, 300..=3000)
+                    .step_by(100.0)
+                    .text("Max size (px)"),
+            )
+            .changed();
+        ui.horizontal(|ui| {
+            changed |= ui
+                .radio_value(&mut settings.format, CoverFormat::Jpeg, "JPEG")
+                .changed();
+            changed |= ui
+                .radio_value(&mut settings.format, CoverFormat::Png, "PNG")
+                .changed();
+        });
+        if settings.format == CoverFormat::Jpeg {
+            changed |= ui
+                .add(egui::Slider::new(&mut settings.jpeg_quality, 50..=100).text("JPEG quality"))
+                .changed();
+        }
+        changed |= ui
+            .checkbox(
+                &mut settings.folder_cover,
+                "Write cover file into album folders",
+            )
+            .changed();
+    });
+    changed
+}
@@ -89,7 +89,7 @@ impl LangThing {
                 .max_height(350.0)
                 .show(ui, |ui| {
                     for (lang, code) in languages.iter().zip(language_codes.iter()) {
-                        if lang_in == code.to_string() {
+                        if lang_in == *code {
                             if ui
                                 .add(egui::Button::new(
                                     egui::RichText::new(*lang).color(Color32::LIGHT_BLUE),
@@ -123,3 +123,49 @@ impl LangThing {
         lang_in
     }
 }
+
+/// ISO 639-2 code for the ID3 language fields, "XXX" when unknown.
+pub fn iso639_2(code: &str) -> [u8; 3] {
+    let three = match code {
+        "en" => "eng",
+        "fr" => "fra",
+        "es" => "spa",
+        "zh" => "zho",
+        "de" => "deu",
+        "ja" => "jpn",
+        "ar" => "ara",
+        "ru" => "rus",
+        "it" => "ita",
+        "pt" => "por",
+        "nl" => "nld",
+        "sv" => "swe",
+        "no" => "nor",
+        "fi" => "fin",
+        "da" => "dan",
+        "pl" => "pol",
+        "cs" => "ces",
+        "hu" => "hun",
+        "ro" => "ron",
+        "tr" => "tur",
+        "vi" => "vie",
+        "ko" => "kor",
+        "el" => "ell",
+        "he" => "heb",
+        "th" => "tha",
+        "id" => "ind",
+        "ms" => "msa",
+        "hi" => "hin",
+        "uk" => "ukr",
+        "bg" => "bul",
+        "hr" => "hrv",
+        "sk" => "slk",
+        "sl" => "slv",
+        "sr" => "srp",
+        "lt" => "lit",
+        "lv" => "lav",
+        "et" => "est",
+        _ => "XXX",
+    };
+    let b = three.as_bytes();
+    [b[0], b[1], b[2]]
+}
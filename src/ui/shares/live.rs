@@ -0,0 +1,255 @@
+use crate::ui::shares::process::Running;
+use chrono::{Local, NaiveTime, TimeDelta};
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum LiveStop {
+    Manual,
+    After { minutes: u32 },
+    At { time: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LiveSettings {
+    /// Wait for a scheduled stream or premiere, polling every `wait_retry` seconds.
+    pub wait: bool,
+    pub wait_retry: u32,
+    pub from_start: bool,
+    pub stop: LiveStop,
+}
+
+impl Default for LiveSettings {
+    fn default() -> Self {
+        Self {
+            wait: true,
+            wait_retry: 60,
+            from_start: false,
+            stop: LiveStop::Manual,
+        }
+    }
+}
+
+/// Shared with the window so it can show how long and how much has been recorded.
+#[derive(Debug, Default)]
+pub struct LiveProgress {
+    pub elapsed: AtomicU64,
+    pub bytes: AtomicU64,
+}
+
+impl LiveProgress {
+    pub fn reset(&self) {
+        self.elapsed.store(0, Ordering::Relaxed);
+        self.bytes.store(0, Ordering::Relaxed);
+    }
+    pub fn text(&self) -> String {
+        let secs = self.elapsed.load(Ordering::Relaxed);
+        let mb = self.bytes.load(Ordering::Relaxed) as f64 / 1_048_576.0;
+        format!(
+            "Recording {:02}:{:02}:{:02} - {mb:.1} MB",
+            secs / 3600,
+            secs / 60 % 60,
+            secs % 60
+        )
+    }
+}
+
+pub fn apply(yt: &mut Command, live: &LiveSettings, format: i8) {
+    if live.wait {
+        yt.arg("--wait-for-video")
+            .arg(live.wait_retry.max(1).to_string());
+    }
+    if live.from_start {
+        yt.arg("--live-from-start");
+    } else {
+        yt.arg("--no-live-from-start");
+    }
+    // MPEG-TS survives being stopped half way, the remux makes the final container.
+    yt.arg("--hls-use-mpegts")
+        .arg("--remux-video")
+        .arg(if format == 2 { "mp4" } else { "mkv" });
+}
+
+// A duration counts from the first recorded bytes, not from the time spent waiting.
+fn stop_deadline(stop: &LiveStop, started: Option<Instant>) -> Option<Instant> {
+    match stop {
+        LiveStop::Manual => None,
+        LiveStop::After { minutes } => {
+            Some(started? + Duration::from_secs(u64::from(*minutes) * 60))
+        }
+        LiveStop::At { time } => {
+            let target = NaiveTime::parse_from_str(time.trim(), "%H:%M").ok()?;
+            let now = Local::now().naive_local();
+            let mut at = now.date().and_time(target);
+            if at <= now {
+                at += TimeDelta::days(1);
+            }
+            Some(Instant::now() + (at - now).to_std().ok()?)
+        }
+    }
+}
+
+// The recording's file name without extension, once yt-dlp has printed it.
+fn recording_stem(name_file: &Path) -> Option<String> {
+    let printed = fs::read_to_string(name_file).ok()?;
+    let path = Path::new(printed.lines().next()?.trim());
+    Some(path.file_stem()?.to_string_lossy().into_owned())
+}
+
+// The recording's fragments and parts written since it started. Other files in
+// the directory don't count.
+fn recorded_bytes(directory: &Path, stem: &str, since: SystemTime) -> u64 {
+    fs::read_dir(directory)
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok())
+                .filter(|e| e.file_name().to_string_lossy().starts_with(stem))
+                .filter_map(|e| e.metadata().ok())
+                .filter(|m| m.is_file() && m.modified().map(|t| t >= since).unwrap_or(false))
+                .map(|m| m.len())
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
+fn read_all(mut pipe: impl Read + Send + 'static) -> thread::JoinHandle<String> {
+    thread::spawn(move || {
+        let mut buf = Vec::new();
+        let _ = pipe.read_to_end(&mut buf);
+        String::from_utf8_lossy(&buf).into_owned()
+    })
+}
+
+/// Runs the recording, updating `progress` every second and sending yt-dlp SIGINT at the
+/// stop time so it finishes the file and remuxes it. Returns stdout and stderr.
+pub fn record(
+    yt: &mut Command,
+    live: &LiveSettings,
+    directory: &str,
+    progress: &LiveProgress,
+    running: &Running,
+) -> (String, String) {
+    progress.reset();
+    let since = SystemTime::now();
+    let mut deadline = stop_deadline(&live.stop, None);
+    let name_file = std::env::temp_dir().join(format!("azulbox-live-{}.name", std::process::id()));
+    let _ = fs::remove_file(&name_file);
+    yt.arg("--print-to-file")
+        .arg("video:%(filename)s")
+        .arg(&name_file);
+    let mut stem: Option<String> = None;
+    let mut child = match yt.stdout(Stdio::piped()).stderr(Stdio::piped()).spawn() {
+        Ok(c) => c,
+        Err(e) => return (String::new(), e.to_string()),
+    };
+    running.set(child.id());
+    let out = read_all(child.stdout.take().unwrap());
+    let err = read_all(child.stderr.take().unwrap());
+
+    let mut started: Option<Instant> = None;
+    let mut stopping = false;
+    loop {
+        match child.try_wait() {
+            Ok(Some(_)) | Err(_) => break,
+            Ok(None) => {}
+        }
+        if stem.is_none() {
+            stem = recording_stem(&name_file);
+        }
+        let bytes = stem
+            .as_deref()
+            .map_or(0, |s| recorded_bytes(Path::new(directory), s, since));
+        if bytes > 0 && started.is_none() {
+            started = Some(Instant::now());
+            deadline = deadline.or(stop_deadline(&live.stop, started));
+        }
+        if let Some(s) = started {
+            progress
+                .elapsed
+                .store(s.elapsed().as_secs(), Ordering::Relaxed);
+        }
+        progress.bytes.store(bytes, Ordering::Relaxed);
+        if !stopping && deadline.is_some_and(|d| Instant::now() >= d) {
+            println!("live: Stop time reached");
+            let _ = Command::new("kill")
+                .arg("-INT")
+                .arg(child.id().to_string())
+                .output();
+            stopping = true;
+        }
+        thread::sleep(Duration::from_secs(1));
+    }
+    running.clear();
+    let _ = fs::remove_file(&name_file);
+    (
+        out.join().unwrap_or_default(),
+        err.join().unwrap_or_default(),
+    )
+}
+
+pub fn live_ui(ui: &mut egui::Ui, live: &mut LiveSettings) -> bool {
+    let mut changed = false;
+    ui.horizontal(|ui| {
+        changed |= ui
+            .checkbox(&mut live.wait, "Wait for start")
+            .on_hover_text("For scheduled streams and premieres")
+            .changed();
+        changed |= ui
+            .radio_value(&mut live.from_start, true, "From beginning")
+            .changed();
+        changed |= ui
+            .radio_value(&mut live.from_start, false, "From now")
+            .changed();
+    });
+    ui.horizontal(|ui| {
+        ui.label("Stop: ");
+        let mut kind = match live.stop {
+            LiveStop::Manual => 0,
+            LiveStop::After { .. } => 1,
+            LiveStop::At { .. } => 2,
+        };
+        let before = kind;
+        ui.radio_value(&mut kind, 0, "Manual");
+        ui.radio_value(&mut kind, 1, "After");
+        ui.radio_value(&mut kind, 2, "At");
+        if kind != before {
+            live.stop = match kind {
+                1 => LiveStop::After { minutes: 60 },
+                2 => LiveStop::At {
+                    time: Local::now().format("%H:%M").to_string(),
+                },
+                _ => LiveStop::Manual,
+            };
+            changed = true;
+        }
+        match &mut live.stop {
+            LiveStop::Manual => {}
+            LiveStop::After { minutes } => {
+                changed |= ui
+                    .add(egui::DragValue::new(minutes).range(1..=1440).suffix(" min"))
+                    .changed();
+            }
+            LiveStop::At { time } => {
+                let edit = ui.add(
+                    egui::TextEdit::singleline(time)
+                        .desired_width(60.0)
+                        .hint_text("HH:MM"),
+                );
+                changed |= edit.lost_focus();
+                if NaiveTime::parse_from_str(time.trim(), "%H:%M").is_err() {
+                    ui.colored_label(egui::Color32::LIGHT_RED, "HH:MM");
+                }
+            }
+        }
+    });
+    changed
+}
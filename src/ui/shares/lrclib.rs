@@ -1,135 +1,102 @@
-use std::error::Error;
-use std::path::Path;
-
-use lofty::config::WriteOptions;
-use lofty::prelude::*;
-use lofty::probe::Probe;
-use lofty::tag::Tag;
+use crate::ui::shares::lyrics::Lrc;
+use crate::ui::shares::lyrics_providers::{Found, LyricsProvider, Song, match_score};
+use crate::ui::shares::network;
 use serde::Deserialize;
-#[allow(dead_code)]
-pub fn lrclib_fetch(opt: &Path, lang: &str) {
-    let mut tagged_file = Probe::open(&opt)
-        .expect("ERROR: Bad path provided!")
-        .read()
-        .expect("ERROR: Failed to read file!");
+use url::form_urlencoded;
 
-    let tag = match tagged_file.primary_tag_mut() {
-        Some(primary_tag) => primary_tag,
-        None => {
-            if let Some(first_tag) = tagged_file.first_tag_mut() {
-                first_tag
-            } else {
-                let tag_type = tagged_file.primary_tag_type();
+const API: &str = "https://lrclib.net/api";
 
-                eprintln!("WARN: No tags found, creating a new tag of type `{tag_type:?}`");
-                tagged_file.insert_tag(Tag::new(tag_type));
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Record {
+    track_name: String,
+    artist_name: String,
+    duration: Option<f64>,
+    plain_lyrics: Option<String>,
+    synced_lyrics: Option<String>,
+}
 
-                tagged_file.primary_tag_mut().unwrap()
-            }
-        }
-    };
-    let artist = tag.artist().unwrap();
-    let title = tag.title().unwrap();
-    let artist: String = form_urlencoded::byte_serialize(artist.as_bytes()).collect();
-    let title: String = form_urlencoded::byte_serialize(title.as_bytes()).collect();
-    println!("{title}\n{artist}");
-    let query = format!(
-        "https://lrclib.net/api/get?artist_name={}&track_name={}",
-        artist, title
-    );
-    let lyrics = fetch(&query);
-    println!("{}", query);
-    match lyrics {
-        Ok(ly) => {
-            println!("{ly}");
-            if !ly.is_empty() {
-                let lyric_after = translate(lang, &ly);
-                if lyric_after.is_ok() {
-                    let lyric_final = lyric_after.as_ref().ok().unwrap();
-                    if !lyric_final.is_empty() {
-                        tag.insert_text(ItemKey::Lyrics, lyric_after.unwrap());
-                        tag.save_to_path(opt, WriteOptions::default())
-                            .expect("ERROR: Failed to write the tag!");
-                    }
-                }
-            }
-        }
-        Err(e) => {
-            println!("{e}")
-        }
+impl Record {
+    fn found(self, song: &Song) -> Option<Found> {
+        let score = match_score(song, &self.track_name, &self.artist_name, self.duration);
+        let text = self
+            .synced_lyrics
+            .filter(|l| !l.trim().is_empty())
+            .or(self.plain_lyrics)?;
+        Some(Found {
+            lrc: Lrc::parse(&text),
+            score,
+        })
     }
 }
 
-#[derive(Debug, Deserialize)]
-struct ApiResponse {
-    #[serde(rename = "plainLyrics")]
-    plain_lyrics: String,
-    #[serde(rename = "syncedLyrics")]
-    synced_lyrics: String,
+fn encode(s: &str) -> String {
+    form_urlencoded::byte_serialize(s.as_bytes()).collect()
 }
 
-fn fetch(query: &str) -> Result<String, Box<dyn Error>> {
-    let resp = ureq::get(query)
-        .header("User-Agent", "Azulbox (https://github.com/musdx/azul-box)")
-        .call();
-    let mut retu: String = String::new();
-    match resp {
-        Ok(mut a) => {
-            let json = a.body_mut().read_json::<ApiResponse>();
-            if json.is_ok() {
-                let lyr = json.ok().unwrap();
-                if !lyr.synced_lyrics.is_empty() {
-                    retu = lyr.synced_lyrics;
-                } else if !lyr.plain_lyrics.is_empty() {
-                    retu = lyr.plain_lyrics;
-                } else {
+/// `/api/get`: an exact lookup by artist and title, narrowed by album and duration.
+pub struct Get;
+
+impl LyricsProvider for Get {
+    fn find(&self, song: &Song) -> Option<Found> {
+        if song.artist.trim().is_empty() {
+            return None;
+        }
+        let mut query = format!(
+            "{API}/get?artist_name={}&track_name={}",
+            encode(&song.artist),
+            encode(&song.title)
+        );
+        if let Some(album) = &song.album {
+            query.push_str(&format!("&album_name={}", encode(album)));
+        }
+        if let Some(duration) = song.duration {
+            query.push_str(&format!("&duration={duration}"));
+        }
+        println!("{query}");
+        match network::get(&query) {
+            Ok(mut resp) => match resp.body_mut().read_json::<Record>() {
+                Ok(record) => record.found(song),
+                Err(e) => {
+                    println!("lrclib: Bad response {e}");
+                    None
                 }
+            },
+            Err(e) => {
+                println!("lrclib: {e}");
+                None
             }
         }
-        Err(e) => {
-            println!("{e}");
-        }
     }
-    Ok(retu)
 }
 
-use serde_json::Value;
-use url::form_urlencoded;
-
-fn translate(to: &str, text: &str) -> Result<String, Box<dyn std::error::Error>> {
-    let en_text: String = form_urlencoded::byte_serialize(text.as_bytes()).collect();
-    let url = format!(
-        "https://translate.googleapis.com/translate_a/single?client=gtx&sl=auto&tl={}&dt=t&q={}",
-        to, en_text
-    );
+/// `/api/search`: every candidate is scored against the song, the best one returned.
+pub struct Search;
 
-    let mut translated_text = text.to_string();
-
-    let response = ureq::get(&url).call();
-    match response {
-        Ok(mut re) => {
-            let string_body = re.body_mut().read_to_string();
-
-            if string_body.is_ok() {
-                let json_as_string = &string_body.ok().unwrap();
-                let values = serde_json::from_str::<Value>(json_as_string)?;
-                if let Some(value) = values.get(0) {
-                    println!("{:?}", value.as_str());
-                    if let Some(list) = value.as_array() {
-                        let lyrics: Vec<String> = list
-                            .iter()
-                            .filter_map(|v| v.get(0).and_then(|v| v.as_str()))
-                            .map(|s| s.to_string())
-                            .collect();
-                        translated_text = lyrics.join("");
-                        println!("Translate success!");
-                    }
+impl LyricsProvider for Search {
+    fn find(&self, song: &Song) -> Option<Found> {
+        let mut query = format!("{API}/search?track_name={}", encode(&song.title));
+        if !song.artist.trim().is_empty() {
+            query.push_str(&format!("&artist_name={}", encode(&song.artist)));
+        }
+        println!("{query}");
+        let records = match network::get(&query) {
+            Ok(mut resp) => match resp.body_mut().read_json::<Vec<Record>>() {
+                Ok(records) => records,
+                Err(e) => {
+                    println!("lrclib: Bad response {e}");
+                    return None;
                 }
+            },
+            Err(e) => {
+                println!("lrclib: {e}");
+                return None;
             }
-        }
-        Err(e) => {
-            println!("{e}");
-        }
+        };
+        records
+            .into_iter()
+            .filter_map(|r| r.found(song))
+            // Synced lyrics win a tie.
+            .max_by_key(|f| (f.score, f.lrc.synced()))
     }
-    Ok(translated_text)
 }
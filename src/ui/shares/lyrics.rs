@@ -0,0 +1,474 @@
+use crate::ui::shares::lang;
+use lofty::TextEncoding;
+use lofty::config::{ParseOptions, WriteOptions};
+use lofty::file::FileType;
+use lofty::id3::v2::{
+    BinaryFrame, Frame, FrameId, Id3v2Tag, SyncTextContentType, SynchronizedTextFrame,
+    TimestampFormat, UnsynchronizedTextFrame,
+};
+use lofty::iff::aiff::AiffFile;
+use lofty::iff::wav::WavFile;
+use lofty::mpeg::MpegFile;
+use lofty::prelude::*;
+use lofty::probe::Probe;
+use lofty::tag::{ItemValue, Tag, TagItem, TagType};
+use regex::Regex;
+use std::borrow::Cow;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+static STAMP: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\[(\d+):(\d{1,2})(?:[.:](\d{1,3}))?\]").unwrap());
+static ID_TAG: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\[([a-zA-Z#]+):([^\]]*)\]\s*$").unwrap());
+
+/// Vorbis comment and MP4 freeform key for the LRC copy; plain text stays in LYRICS.
+/// Translations go in LYRICS_<LANG>.
+const SYNCED_KEY: &str = "SYNCEDLYRICS";
+const MP4_PREFIX: &str = "----:com.apple.iTunes:";
+/// USLT/SYLT description of the translation frames.
+const TRANSLATION: &str = "Translation";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimedLine {
+    pub time_ms: u32,
+    pub text: String,
+}
+
+/// Lyrics read from LRC or plain text. `lines` is empty for unsynced lyrics,
+/// `plain` then holds the text.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Lrc {
+    /// `[ar:...]`, `[ti:...]` and the like, without `offset`, which is applied to the times.
+    pub tags: Vec<(String, String)>,
+    pub lines: Vec<TimedLine>,
+    pub plain: Vec<String>,
+}
+
+impl Lrc {
+    pub fn parse(text: &str) -> Self {
+        let mut lrc = Lrc::default();
+        let mut offset: i64 = 0;
+        for raw in text.lines() {
+            let line = raw.trim_end_matches('\r');
+            let mut rest = line.trim_start();
+            let mut times = Vec::new();
+            while let Some((time, after)) = parse_stamp(rest) {
+                times.push(time);
+                rest = after;
+            }
+            if !times.is_empty() {
+                for time_ms in times {
+                    lrc.lines.push(TimedLine {
+                        time_ms,
+                        text: rest.trim().to_string(),
+                    });
+                }
+            } else if let Some((key, value)) = parse_tag(line) {
+                if key == "offset" {
+                    offset = value.trim_start_matches('+').parse().unwrap_or(0);
+                } else {
+                    lrc.tags.push((key, value));
+                }
+            } else {
+                lrc.plain.push(line.to_string());
+            }
+        }
+        // A positive offset shows the lyrics sooner.
+        for line in lrc.lines.iter_mut() {
+            line.time_ms = (line.time_ms as i64 - offset).max(0) as u32;
+        }
+        lrc.lines.sort_by_key(|l| l.time_ms);
+        if lrc.synced() {
+            lrc.plain.clear();
+        } else {
+            while lrc.plain.last().is_some_and(|l| l.trim().is_empty()) {
+                lrc.plain.pop();
+            }
+        }
+        lrc
+    }
+
+    pub fn synced(&self) -> bool {
+        !self.lines.is_empty()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty() && self.plain.iter().all(|l| l.trim().is_empty())
+    }
+
+    /// The lyrics without timestamps, for USLT and LYRICS.
+    pub fn plain_text(&self) -> String {
+        if self.synced() {
+            self.lines
+                .iter()
+                .map(|l| l.text.as_str())
+                .collect::<Vec<_>>()
+                .join("\n")
+        } else {
+            self.plain.join("\n")
+        }
+    }
+
+    pub fn to_lrc(&self) -> String {
+        let mut out = String::new();
+        for (key, value) in self.tags.iter() {
+            out.push_str(&format!("[{key}:{value}]\n"));
+        }
+        if !self.synced() {
+            out.push_str(&self.plain_text());
+            return out;
+        }
+        for line in self.lines.iter() {
+            out.push_str(&format!("[{}]{}\n", stamp(line.time_ms), line.text));
+        }
+        out
+    }
+}
+
+/// Splits a leading `[mm:ss.xx]` off a line: the time in ms and the rest.
+pub fn parse_stamp(line: &str) -> Option<(u32, &str)> {
+    let caps = STAMP.captures(line)?;
+    let min: u64 = caps[1].parse().unwrap_or(0);
+    let sec: u64 = caps[2].parse().unwrap_or(0);
+    // ".5" is half a second, ".05" 50 ms, ".005" 5 ms.
+    let frac = caps.get(3).map_or(0, |f| {
+        let digits = f.as_str();
+        digits.parse::<u64>().unwrap_or(0) * 10u64.pow(3 - digits.len() as u32)
+    });
+    let time = (min * 60_000 + sec * 1000 + frac).min(u32::MAX as u64) as u32;
+    Some((time, &line[caps[0].len()..]))
+}
+
+/// An `[ar:Artist]` style line as (key, value), the key lowercased.
+pub fn parse_tag(line: &str) -> Option<(String, String)> {
+    let caps = ID_TAG.captures(line.trim())?;
+    Some((caps[1].to_lowercase(), caps[2].trim().to_string()))
+}
+
+/// `mm:ss.xx`
+pub fn stamp(ms: u32) -> String {
+    format!(
+        "{:02}:{:02}.{:02}",
+        ms / 60_000,
+        ms / 1000 % 60,
+        ms % 1000 / 10
+    )
+}
+
+/// Translated lyrics, stored next to the main ones under their language.
+pub struct Translation {
+    /// Two-letter code, as in the language setting.
+    pub lang: String,
+    pub lrc: Lrc,
+}
+
+/// Writes the lyrics into the file's main tag: a plain copy as USLT/LYRICS and,
+/// for synced lyrics, SYLT for ID3v2 files or SYNCEDLYRICS for Vorbis comments,
+/// APE and MP4. WAV gets them in an ID3v2 chunk; if that can't be written the
+/// plain text goes into the RIFF INFO comment, which has no lyrics field.
+pub fn embed(path: &Path, lrc: &Lrc, translation: Option<&Translation>) -> Result<(), String> {
+    let mut tagged_file = Probe::open(path)
+        .and_then(|p| p.read())
+        .map_err(|e| e.to_string())?;
+    let file_type = tagged_file.file_type();
+    let tag_type = tagged_file.primary_tag_type();
+    if tagged_file.tag(tag_type).is_none() {
+        // ffmpeg only writes RIFF INFO for WAV, copy it so the ID3 chunk has
+        // the title and artist too.
+        let tag = match tagged_file.first_tag() {
+            Some(first) => {
+                let mut tag = first.clone();
+                tag.re_map(tag_type);
+                tag
+            }
+            None => Tag::new(tag_type),
+        };
+        tagged_file.insert_tag(tag);
+    }
+    let Some(tag) = tagged_file.tag_mut(tag_type) else {
+        return Err(format!("no {tag_type:?} tag"));
+    };
+    let plain = lrc.plain_text();
+    tag.insert_text(ItemKey::Lyrics, plain.clone());
+    let prefix = match tag_type {
+        TagType::VorbisComments | TagType::Ape => Some(""),
+        TagType::Mp4Ilst => Some(MP4_PREFIX),
+        _ => None,
+    };
+    if let Some(prefix) = prefix {
+        let key = ItemKey::Unknown(format!("{prefix}{SYNCED_KEY}"));
+        tag.remove_key(&key);
+        if lrc.synced() {
+            tag.insert_unchecked(TagItem::new(key, ItemValue::Text(lrc.to_lrc())));
+        }
+        if let Some(t) = translation {
+            let key = ItemKey::Unknown(format!("{prefix}LYRICS_{}", t.lang.to_uppercase()));
+            tag.remove_key(&key);
+            tag.insert_unchecked(TagItem::new(key, ItemValue::Text(t.lrc.to_lrc())));
+        }
+    }
+    match tag.save_to_path(path, WriteOptions::default()) {
+        Ok(_) if tag_type == TagType::Id3v2 => write_id3_frames(path, file_type, lrc, translation),
+        Ok(_) => Ok(()),
+        Err(e) if file_type == FileType::Wav => {
+            println!("lyrics: Fail to write ID3 chunk {e}, using RIFF INFO");
+            let mut info = tagged_file
+                .tag(TagType::RiffInfo)
+                .cloned()
+                .unwrap_or_else(|| Tag::new(TagType::RiffInfo));
+            info.insert_text(ItemKey::Comment, plain);
+            info.save_to_path(path, WriteOptions::default())
+                .map_err(|e| e.to_string())
+        }
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+// The generic tag has no SYLT and only one USLT, so those go in through the
+// ID3v2 tag itself. Any later save through a generic tag drops them again, so
+// embed lyrics last.
+fn write_id3_frames(
+    path: &Path,
+    file_type: FileType,
+    lrc: &Lrc,
+    translation: Option<&Translation>,
+) -> Result<(), String> {
+    if !lrc.synced() && translation.is_none() {
+        return Ok(());
+    }
+    let Some(id3v2) = read_id3v2(path, file_type)? else {
+        return Ok(());
+    };
+    let mut id3v2 = id3v2.unwrap_or_else(Id3v2Tag::new);
+    set_sylt(&mut id3v2, *b"XXX", None, lrc)?;
+    if let Some(t) = translation {
+        let lang = lang::iso639_2(&t.lang);
+        id3v2.insert(Frame::UnsynchronizedText(UnsynchronizedTextFrame::new(
+            TextEncoding::UTF8,
+            lang,
+            TRANSLATION.to_string(),
+            t.lrc.plain_text(),
+        )));
+        set_sylt(&mut id3v2, lang, Some(TRANSLATION), &t.lrc)?;
+    }
+    id3v2
+        .save_to_path(path, WriteOptions::default())
+        .map_err(|e| e.to_string())
+}
+
+// The ID3v2 tag of the formats that keep one as their main tag. The outer None
+// is for formats without ID3v2, the inner one for files without the tag yet.
+fn read_id3v2(path: &Path, file_type: FileType) -> Result<Option<Option<Id3v2Tag>>, String> {
+    let mut file = File::open(path).map_err(|e| e.to_string())?;
+    let options = ParseOptions::new();
+    let id3v2 = match file_type {
+        FileType::Mpeg => MpegFile::read_from(&mut file, options).map(|f| f.id3v2().cloned()),
+        FileType::Wav => WavFile::read_from(&mut file, options).map(|f| f.id3v2().cloned()),
+        FileType::Aiff => AiffFile::read_from(&mut file, options).map(|f| f.id3v2().cloned()),
+        _ => return Ok(None),
+    };
+    id3v2.map(Some).map_err(|e| e.to_string())
+}
+
+// The untranslated SYLT frame, as written by `set_sylt`.
+fn read_sylt(id3v2: &Id3v2Tag) -> Option<Lrc> {
+    id3v2.into_iter().find_map(|f| match f {
+        Frame::Binary(b) if f.id_str() == "SYLT" => {
+            let sylt = SynchronizedTextFrame::parse(&b.data, b.flags()).ok()?;
+            if sylt.description.is_some() || sylt.timestamp_format != TimestampFormat::MS {
+                return None;
+            }
+            Some(Lrc {
+                lines: sylt
+                    .content
+                    .into_iter()
+                    .map(|(time_ms, text)| TimedLine { time_ms, text })
+                    .collect(),
+                ..Lrc::default()
+            })
+        }
+        _ => None,
+    })
+}
+
+/// The song's lyrics: the `.lrc` sidecar if there is one, then synced lyrics
+/// from the tags, then the plain lyrics tag.
+pub fn read(path: &Path) -> Option<Lrc> {
+    if let Ok(text) = fs::read_to_string(sidecar_path(path)) {
+        let lrc = Lrc::parse(&text);
+        if !lrc.is_empty() {
+            return Some(lrc);
+        }
+    }
+    let tagged_file = match Probe::open(path).and_then(|p| p.read()) {
+        Ok(f) => f,
+        Err(e) => {
+            println!("lyrics: Fail to open {path:?} {e}");
+            return None;
+        }
+    };
+    if let Ok(Some(Some(id3v2))) = read_id3v2(path, tagged_file.file_type())
+        && let Some(lrc) = read_sylt(&id3v2)
+    {
+        return Some(lrc);
+    }
+    let tag = tagged_file
+        .primary_tag()
+        .or_else(|| tagged_file.first_tag())?;
+    [
+        ItemKey::Unknown(SYNCED_KEY.to_string()),
+        ItemKey::Unknown(format!("{MP4_PREFIX}{SYNCED_KEY}")),
+        ItemKey::Lyrics,
+    ]
+    .iter()
+    .filter_map(|key| tag.get_string(key))
+    .map(Lrc::parse)
+    .find(|lrc| !lrc.is_empty())
+}
+
+// SYLT is read back as a binary frame, which `insert` only dedupes on equal
+// bytes, so the old frame with the same language and description goes first.
+fn set_sylt(
+    id3v2: &mut Id3v2Tag,
+    lang: [u8; 3],
+    description: Option<&str>,
+    lrc: &Lrc,
+) -> Result<(), String> {
+    id3v2.retain(|f| match f {
+        Frame::Binary(b) if f.id_str() == "SYLT" => {
+            match SynchronizedTextFrame::parse(&b.data, b.flags()) {
+                Ok(old) => old.language != lang || old.description.as_deref() != description,
+                Err(_) => true,
+            }
+        }
+        _ => true,
+    });
+    if !lrc.synced() {
+        return Ok(());
+    }
+    let sylt = SynchronizedTextFrame::new(
+        TextEncoding::UTF8,
+        lang,
+        TimestampFormat::MS,
+        SyncTextContentType::Lyrics,
+        description.map(|d| d.to_string()),
+        lrc.lines
+            .iter()
+            .map(|l| (l.time_ms, l.text.clone()))
+            .collect(),
+    );
+    let data = sylt.as_bytes().map_err(|e| e.to_string())?;
+    id3v2.insert(Frame::Binary(BinaryFrame::new(
+        FrameId::Valid(Cow::Borrowed("SYLT")),
+        data,
+    )));
+    Ok(())
+}
+
+/// Runs `save`, a step that writes through a generic tag, and puts back the
+/// SYLT and translation USLT frames it drops. For tagging done after `embed`.
+pub fn keep_frames(path: &Path, save: impl FnOnce()) {
+    let file_type = match Probe::open(path).and_then(|p| p.read()) {
+        Ok(tagged_file) => tagged_file.file_type(),
+        Err(e) => {
+            println!("lyrics: Fail to open {path:?} {e}");
+            return save();
+        }
+    };
+    let kept: Vec<Frame<'static>> = match read_id3v2(path, file_type) {
+        Ok(Some(Some(id3v2))) => id3v2
+            .into_iter()
+            .filter(|f| match f {
+                Frame::UnsynchronizedText(u) => !u.description.is_empty(),
+                _ => f.id_str() == "SYLT",
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+    save();
+    if kept.is_empty() {
+        return;
+    }
+    let result = read_id3v2(path, file_type).and_then(|id3v2| {
+        let mut id3v2 = id3v2.flatten().unwrap_or_else(Id3v2Tag::new);
+        for frame in kept {
+            id3v2.insert(frame);
+        }
+        id3v2
+            .save_to_path(path, WriteOptions::default())
+            .map_err(|e| e.to_string())
+    });
+    if let Err(e) = result {
+        println!("lyrics: Fail to restore lyrics frames {e}");
+    }
+}
+
+/// `song.flac` -> `song.lrc`, the name players look for.
+pub fn sidecar_path(audio: &Path) -> PathBuf {
+    audio.with_extension("lrc")
+}
+
+pub fn write_sidecar(audio: &Path, lrc: &Lrc) {
+    let path = sidecar_path(audio);
+    if let Err(e) = fs::write(&path, lrc.to_lrc()) {
+        println!("lyrics: Fail to write {path:?} {e}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stamps_and_fractions() {
+        assert_eq!(parse_stamp("[01:02.5]x"), Some((62_500, "x")));
+        assert_eq!(parse_stamp("[00:01.05]"), Some((1_050, "")));
+        assert_eq!(parse_stamp("[00:01:005]"), Some((1_005, "")));
+        assert_eq!(parse_stamp("[00:01]"), Some((1_000, "")));
+        assert_eq!(parse_stamp("no stamp"), None);
+        assert_eq!(stamp(62_500), "01:02.50");
+    }
+
+    #[test]
+    fn synced_lines_sorted_with_repeats_and_offset() {
+        let lrc = Lrc::parse(
+            "[ar:Someone]\n[offset:+500]\n[00:10.00][00:02.00]Chorus\r\n[00:05.00] Verse\n",
+        );
+        assert_eq!(lrc.tags, vec![("ar".to_string(), "Someone".to_string())]);
+        let times: Vec<_> = lrc
+            .lines
+            .iter()
+            .map(|l| (l.time_ms, l.text.as_str()))
+            .collect();
+        assert_eq!(
+            times,
+            vec![(1_500, "Chorus"), (4_500, "Verse"), (9_500, "Chorus")]
+        );
+        assert!(lrc.plain.is_empty());
+    }
+
+    #[test]
+    fn negative_offset_and_clamp() {
+        let lrc = Lrc::parse("[offset:-250]\n[00:00.00]a\n");
+        assert_eq!(lrc.lines[0].time_ms, 250);
+        let lrc = Lrc::parse("[offset:1000]\n[00:00.50]a\n");
+        assert_eq!(lrc.lines[0].time_ms, 0);
+    }
+
+    #[test]
+    fn plain_text_keeps_lines_and_drops_trailing_blanks() {
+        let lrc = Lrc::parse("first\n\nsecond\n\n\n");
+        assert!(!lrc.synced());
+        assert_eq!(lrc.plain, vec!["first", "", "second"]);
+        assert_eq!(lrc.plain_text(), "first\n\nsecond");
+        assert!(Lrc::parse("\n  \n").is_empty());
+    }
+
+    #[test]
+    fn round_trips_through_to_lrc() {
+        let lrc = Lrc::parse("[ti:Song]\n[00:01.20]one\n[01:00.00]two\n");
+        assert_eq!(Lrc::parse(&lrc.to_lrc()), lrc);
+    }
+}
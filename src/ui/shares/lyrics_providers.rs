@@ -0,0 +1,550 @@
+use crate::ui::shares::archive;
+use crate::ui::shares::config::{self, Config, get_config_dir_path};
+use crate::ui::shares::lrclib;
+use crate::ui::shares::lyrics::{self, Lrc, Translation};
+use crate::ui::shares::notify::notification_summary;
+use crate::ui::shares::process::FileLock;
+use crate::ui::shares::translate::{self, TranslateSettings};
+use eframe::egui;
+use lofty::prelude::*;
+use lofty::probe::Probe;
+use native_dialog::DialogBuilder;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+/// What the providers know about the song being looked up.
+pub struct Song {
+    pub file: PathBuf,
+    pub title: String,
+    pub artist: String,
+    pub album: Option<String>,
+    pub duration: Option<u64>,
+    /// The .lrc yt-dlp made from the video's subtitles.
+    pub subtitle: Option<PathBuf>,
+}
+
+impl Song {
+    /// Reads the tags, falling back to the file name for the title.
+    pub fn read(file: &Path, subtitle: Option<&Path>) -> Self {
+        let mut song = Song {
+            file: file.to_path_buf(),
+            title: file
+                .file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            artist: String::new(),
+            album: None,
+            duration: None,
+            subtitle: subtitle.map(|s| s.to_path_buf()),
+        };
+        match Probe::open(file).and_then(|p| p.read()) {
+            Ok(tagged_file) => {
+                let secs = tagged_file.properties().duration().as_secs();
+                song.duration = (secs > 0).then_some(secs);
+                // WAV may only have RIFF INFO, so read from whichever tag is there.
+                if let Some(tag) = tagged_file
+                    .primary_tag()
+                    .or_else(|| tagged_file.first_tag())
+                {
+                    if let Some(title) = tag.title().filter(|t| !t.trim().is_empty()) {
+                        song.title = title.to_string();
+                    }
+                    song.artist = tag.artist().map(|a| a.to_string()).unwrap_or_default();
+                    song.album = tag
+                        .album()
+                        .map(|a| a.to_string())
+                        .filter(|a| !a.trim().is_empty());
+                }
+            }
+            Err(e) => println!("lyrics: Fail to read {file:?} {e}"),
+        }
+        song
+    }
+}
+
+pub struct Found {
+    pub lrc: Lrc,
+    /// 0 to 100, how sure the provider is this is the right song.
+    pub score: u8,
+}
+
+pub trait LyricsProvider {
+    fn find(&self, song: &Song) -> Option<Found>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProviderKind {
+    Subtitles,
+    LrclibGet,
+    LrclibSearch,
+    LocalFolders,
+}
+
+impl ProviderKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            ProviderKind::Subtitles => "Video subtitles",
+            ProviderKind::LrclibGet => "LRCLIB",
+            ProviderKind::LrclibSearch => "LRCLIB search",
+            ProviderKind::LocalFolders => "Local .lrc folders",
+        }
+    }
+    fn provider(self, settings: &LyricsProviders) -> Box<dyn LyricsProvider + '_> {
+        match self {
+            ProviderKind::Subtitles => Box::new(Subtitles),
+            ProviderKind::LrclibGet => Box::new(lrclib::Get),
+            ProviderKind::LrclibSearch => Box::new(lrclib::Search),
+            ProviderKind::LocalFolders => Box::new(LocalFolders {
+                folders: &settings.folders,
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProviderEntry {
+    pub kind: ProviderKind,
+    pub enabled: bool,
+}
+
+/// Providers are asked in order; the first result at or above `min_score` wins.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LyricsProviders {
+    pub order: Vec<ProviderEntry>,
+    pub min_score: u8,
+    pub folders: Vec<String>,
+}
+
+impl Default for LyricsProviders {
+    fn default() -> Self {
+        Self {
+            order: Vec::new(),
+            min_score: 70,
+            folders: Vec::new(),
+        }
+    }
+}
+
+impl LyricsProviders {
+    /// Configs from before the provider list only have the LRCLIB on/off switch.
+    pub fn or_legacy(mut self, lrclib: bool) -> Self {
+        if self.order.is_empty() {
+            self.order = default_order(lrclib);
+        }
+        self
+    }
+}
+
+fn default_order(lrclib: bool) -> Vec<ProviderEntry> {
+    [
+        (ProviderKind::Subtitles, true),
+        (ProviderKind::LocalFolders, true),
+        (ProviderKind::LrclibGet, lrclib),
+        (ProviderKind::LrclibSearch, lrclib),
+    ]
+    .into_iter()
+    .map(|(kind, enabled)| ProviderEntry { kind, enabled })
+    .collect()
+}
+
+/// Asks the enabled providers in order.
+pub fn find(settings: &LyricsProviders, song: &Song) -> Option<(ProviderKind, Found)> {
+    for entry in settings.order.iter().filter(|e| e.enabled) {
+        match entry.kind.provider(settings).find(song) {
+            Some(found) if found.score >= settings.min_score && !found.lrc.is_empty() => {
+                println!(
+                    "lyrics: {} matched \"{}\" ({})",
+                    entry.kind.name(),
+                    song.title,
+                    found.score
+                );
+                return Some((entry.kind, found));
+            }
+            Some(found) => println!(
+                "lyrics: {} only scored {} for \"{}\"",
+                entry.kind.name(),
+                found.score,
+                song.title
+            ),
+            None => {}
+        }
+    }
+    None
+}
+
+struct Subtitles;
+
+impl LyricsProvider for Subtitles {
+    fn find(&self, song: &Song) -> Option<Found> {
+        let text = fs::read_to_string(song.subtitle.as_ref()?).ok()?;
+        Some(Found {
+            lrc: Lrc::parse(&text),
+            score: 100,
+        })
+    }
+}
+
+struct LocalFolders<'a> {
+    folders: &'a [String],
+}
+
+impl LyricsProvider for LocalFolders<'_> {
+    fn find(&self, song: &Song) -> Option<Found> {
+        let mut files = Vec::new();
+        for folder in self.folders.iter().filter(|f| !f.trim().is_empty()) {
+            lrc_files(Path::new(folder), 3, &mut files);
+        }
+        let wanted = format!("{} - {}", song.artist, song.title);
+        let mut best: Option<(u8, PathBuf)> = None;
+        for file in files {
+            let stem = file
+                .file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let by_name = similarity(&stem, &wanted).max(similarity(&stem, &song.title) * 0.9);
+            let score = (by_name * 100.0).round() as u8;
+            if best.as_ref().is_none_or(|(s, _)| score > *s) {
+                best = Some((score, file));
+            }
+        }
+        let (name_score, file) = best?;
+        let lrc = Lrc::parse(&fs::read_to_string(&file).ok()?);
+        // The [ti:]/[ar:] tags are a better guide than the file name when present.
+        let tag = |key: &str| {
+            lrc.tags
+                .iter()
+                .find(|(k, _)| k == key)
+                .map(|(_, v)| v.clone())
+        };
+        let score = match (tag("ti"), tag("ar")) {
+            (Some(ti), Some(ar)) => match_score(song, &ti, &ar, None),
+            _ => name_score,
+        };
+        Some(Found { lrc, score })
+    }
+}
+
+fn lrc_files(dir: &Path, depth: u8, out: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() && depth > 0 {
+            lrc_files(&path, depth - 1, out);
+        } else if path.extension().and_then(|e| e.to_str()) == Some("lrc") {
+            out.push(path);
+        }
+    }
+}
+
+fn normalize(s: &str) -> Vec<char> {
+    let lower = s.to_lowercase();
+    let cleaned: String = lower
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect();
+    cleaned
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .chars()
+        .collect()
+}
+
+/// 0.0 to 1.0, from the edit distance of the normalized strings.
+pub fn similarity(a: &str, b: &str) -> f32 {
+    let (a, b) = (normalize(a), normalize(b));
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut cur = vec![0; b.len() + 1];
+    for (i, ca) in a.iter().enumerate() {
+        cur[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let cost = usize::from(ca != cb);
+            cur[j + 1] = (prev[j] + cost).min(prev[j + 1] + 1).min(cur[j] + 1);
+        }
+        std::mem::swap(&mut prev, &mut cur);
+    }
+    1.0 - prev[b.len()] as f32 / a.len().max(b.len()) as f32
+}
+
+/// Title counts most, then artist, then how close the durations are.
+pub fn match_score(song: &Song, title: &str, artist: &str, duration: Option<f64>) -> u8 {
+    let t = similarity(&song.title, title);
+    let a = if song.artist.trim().is_empty() {
+        t
+    } else {
+        similarity(&song.artist, artist)
+    };
+    let d = match (song.duration, duration) {
+        // Full marks within 2 s, nothing past 15 s.
+        (Some(x), Some(y)) => 1.0 - (((x as f64 - y).abs() - 2.0).max(0.0) / 13.0).min(1.0) as f32,
+        _ => (t + a) / 2.0,
+    };
+    ((t * 0.55 + a * 0.35 + d * 0.10) * 100.0).round() as u8
+}
+
+/// Settings for one lyrics lookup, taken from the music job.
+pub struct LyricsJob<'a> {
+    pub providers: &'a LyricsProviders,
+    pub lang: &'a str,
+    pub translate: &'a TranslateSettings,
+    pub sidecar: bool,
+}
+
+/// Finds, translates and embeds the lyrics for one file, then records which
+/// provider they came from. The subtitle .lrc is removed unless it became the sidecar.
+pub fn work(file: &Path, subtitle: Option<&Path>, job: &LyricsJob) {
+    let song = Song::read(file, subtitle);
+    let mut kept = false;
+    let found = find(job.providers, &song);
+    if let Some((kind, found)) = &found {
+        // Subtitles were already downloaded in the chosen language.
+        let translation = if *kind == ProviderKind::Subtitles {
+            None
+        } else {
+            match translate::translate_lrc(&found.lrc, job.lang, job.translate) {
+                Ok(t) if t != found.lrc => Some(Translation {
+                    lang: job.lang.to_string(),
+                    lrc: t,
+                }),
+                Ok(_) => None,
+                Err(e) => {
+                    println!("lyrics: Fail to translate {e}");
+                    None
+                }
+            }
+        };
+        let lrc = match &translation {
+            Some(t) => translate::combine(&found.lrc, &t.lrc, job.translate.mode),
+            None => found.lrc.clone(),
+        };
+        let embedded = match lyrics::embed(file, &lrc, translation.as_ref()) {
+            Ok(_) => true,
+            Err(e) => {
+                println!("lyrics: Fail to embed lyrics {e}");
+                false
+            }
+        };
+        if job.sidecar || !embedded {
+            lyrics::write_sidecar(file, &lrc);
+            kept = true;
+        }
+    }
+    if let Some(sub) = subtitle
+        && !(kept && sub == lyrics::sidecar_path(file))
+    {
+        let _ = fs::remove_file(sub);
+    }
+    record(
+        &song,
+        found.as_ref().map(|(kind, found)| (*kind, found.score)),
+    );
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LyricsEntry {
+    pub file: String,
+    pub title: String,
+    pub artist: String,
+    /// None when no provider had lyrics.
+    pub provider: Option<ProviderKind>,
+    pub score: u8,
+    pub date: u64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct LyricsRecord {
+    /// When the songs without lyrics were last retried.
+    #[serde(default)]
+    last_retry: u64,
+    songs: Vec<LyricsEntry>,
+}
+
+fn record_file() -> PathBuf {
+    get_config_dir_path().join("lyrics.toml")
+}
+
+fn load_record() -> LyricsRecord {
+    match fs::read_to_string(record_file()) {
+        Ok(s) => toml::from_str(&s).unwrap_or_else(|e| {
+            eprintln!("lyrics: Fail to read record {e}");
+            LyricsRecord::default()
+        }),
+        Err(_) => LyricsRecord::default(),
+    }
+}
+
+fn save_record(record: &LyricsRecord) {
+    match toml::to_string(record) {
+        Ok(s) => {
+            if let Err(e) = fs::write(record_file(), s) {
+                eprintln!("lyrics: Fail to save record {e}");
+            }
+        }
+        Err(e) => eprintln!("lyrics: Fail to serialize record {e}"),
+    }
+}
+
+fn record(song: &Song, provider: Option<(ProviderKind, u8)>) {
+    let file = song.file.to_string_lossy().into_owned();
+    let mut record = load_record();
+    record.songs.retain(|e| e.file != file);
+    record.songs.push(LyricsEntry {
+        file,
+        title: song.title.clone(),
+        artist: song.artist.clone(),
+        provider: provider.map(|(kind, _)| kind),
+        score: provider.map(|(_, score)| score).unwrap_or(0),
+        date: archive::now(),
+    });
+    save_record(&record);
+}
+
+// A retry running in this process. Other processes are kept out by the lock file.
+static RETRYING: AtomicBool = AtomicBool::new(false);
+
+pub fn is_retrying() -> bool {
+    RETRYING.load(Ordering::Relaxed)
+}
+
+/// Songs recorded without lyrics that are still on disk.
+pub fn missing() -> Vec<LyricsEntry> {
+    load_record()
+        .songs
+        .into_iter()
+        .filter(|e| e.provider.is_none() && Path::new(&e.file).exists())
+        .collect()
+}
+
+/// Asks the configured providers again for every song without lyrics and embeds
+/// what turns up. Songs that were moved or deleted are dropped from the record.
+/// Returns how many songs got lyrics, or None if a retry was already running.
+pub fn retry_missing(configs: &Config) -> Option<usize> {
+    if RETRYING.swap(true, Ordering::Relaxed) {
+        return None;
+    }
+    let Some(_lock) = FileLock::acquire("lyrics-retry") else {
+        println!("lyrics: Another process is retrying");
+        RETRYING.store(false, Ordering::Relaxed);
+        return None;
+    };
+    let mut record = load_record();
+    record
+        .songs
+        .retain(|e| e.provider.is_some() || Path::new(&e.file).exists());
+    save_record(&record);
+
+    let providers = configs
+        .music_dl
+        .providers
+        .clone()
+        .or_legacy(configs.music_dl.liblrc);
+    let job = LyricsJob {
+        providers: &providers,
+        lang: &configs.universal.language,
+        translate: &configs.music_dl.translate,
+        sidecar: configs.music_dl.lrc_sidecar,
+    };
+    let queue = missing();
+    for entry in queue.iter() {
+        println!("lyrics: Retrying {}", entry.file);
+        work(Path::new(&entry.file), None, &job);
+    }
+
+    let mut record = load_record();
+    let found = record
+        .songs
+        .iter()
+        .filter(|e| e.provider.is_some() && queue.iter().any(|q| q.file == e.file))
+        .count();
+    record.last_retry = archive::now();
+    save_record(&record);
+
+    if found > 0 {
+        let _ = notification_summary(&format!("Lyrics: found {found} of {} missing", queue.len()));
+    }
+    RETRYING.store(false, Ordering::Relaxed);
+    Some(found)
+}
+
+/// Background retry for the running app, every `lyrics_retry_hours` (0 turns it off).
+/// The config is re-read every minute like the subscription checker.
+pub fn start_retry_scheduler() {
+    thread::spawn(|| {
+        loop {
+            if let Ok(configs) = config::load_config(&config::get_config_file_path()) {
+                let hours = u64::from(configs.music_dl.lyrics_retry_hours);
+                if configs.music_dl.lyrics
+                    && hours > 0
+                    && archive::now().saturating_sub(load_record().last_retry) >= hours * 3600
+                {
+                    retry_missing(&configs);
+                }
+            }
+            thread::sleep(Duration::from_secs(60));
+        }
+    });
+}
+
+/// Order, switches and the minimum score. Returns true when Save was pressed.
+pub fn providers_menu(ui: &mut egui::Ui, settings: &mut LyricsProviders) -> bool {
+    let mut up = None;
+    egui::Grid::new("lyrics_providers_grid").show(ui, |ui| {
+        for (i, entry) in settings.order.iter_mut().enumerate() {
+            ui.checkbox(&mut entry.enabled, entry.kind.name());
+            if ui
+                .add_enabled(i > 0, egui::Button::new("^").small())
+                .on_hover_text("Ask earlier")
+                .clicked()
+            {
+                up = Some(i);
+            }
+            ui.end_row();
+        }
+    });
+    if let Some(i) = up {
+        settings.order.swap(i, i - 1);
+    }
+    ui.add(egui::Slider::new(&mut settings.min_score, 0..=100).text("Minimum match score"));
+    ui.separator();
+    ui.label("Local .lrc folders:");
+    let mut remove = None;
+    for (i, folder) in settings.folders.iter().enumerate() {
+        ui.horizontal(|ui| {
+            if ui.small_button("x").on_hover_text("Remove").clicked() {
+                remove = Some(i);
+            }
+            ui.label(folder);
+        });
+    }
+    if let Some(i) = remove {
+        settings.folders.remove(i);
+    }
+    let mut save = false;
+    ui.horizontal(|ui| {
+        if ui.button("Add folder").clicked()
+            && let Ok(Some(path)) = DialogBuilder::file().open_single_dir().show()
+        {
+            settings.folders.push(path.to_string_lossy().into_owned());
+        }
+        if ui.button("Defaults").clicked() {
+            settings.order = default_order(true);
+            settings.min_score = LyricsProviders::default().min_score;
+        }
+        if ui.button("Save").clicked() {
+            save = true;
+            ui.close_menu();
+        }
+    });
+    save
+}
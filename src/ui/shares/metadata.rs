@@ -0,0 +1,99 @@
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+use std::process::Command;
+
+/// One yt-dlp `--parse-metadata FROM:TO` step. `from` is a field name or an output
+/// template, `to` a `%(meta_field)s` pattern. Rules run top to bottom and a rule
+/// only overwrites when its source has a value, so later rules win.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MetaRule {
+    pub from: String,
+    pub to: String,
+}
+
+fn rule(from: &str, to: &str) -> MetaRule {
+    MetaRule {
+        from: from.to_string(),
+        to: to.to_string(),
+    }
+}
+
+/// Fallbacks first (uploader, "Artist - Title" in the title), then the structured
+/// fields YouTube Music, Bandcamp and SoundCloud provide.
+pub fn default_rules() -> Vec<MetaRule> {
+    vec![
+        rule("%(uploader,channel)s", "%(meta_artist)s"),
+        rule("title", "(?P<meta_artist>.+?) - (?P<meta_title>.+)"),
+        rule("%(track)s", "%(meta_title)s"),
+        rule("%(artist,creator)s", "%(meta_artist)s"),
+        rule("%(album)s", "%(meta_album)s"),
+        rule("%(album_artist)s", "%(meta_album_artist)s"),
+        rule("%(track_number)s", "%(meta_track)s"),
+        rule("%(disc_number)s", "%(meta_disc)s"),
+        rule("%(release_year,upload_date>%Y)s", "%(meta_date)s"),
+        rule("%(genre)s", "%(meta_genre)s"),
+    ]
+}
+
+pub fn apply(yt: &mut Command, rules: &[MetaRule]) {
+    // Missing fields become "" instead of "NA", which the `.+` behind every
+    // %(meta_x)s can't match, so an absent field never clobbers a fallback.
+    yt.arg("--output-na-placeholder").arg("");
+    for r in rules.iter() {
+        if r.from.trim().is_empty() || r.to.trim().is_empty() {
+            continue;
+        }
+        yt.arg("--parse-metadata")
+            .arg(format!("{}:{}", r.from.trim(), r.to.trim()));
+    }
+}
+
+/// Editable rule table. Returns true when Save was pressed.
+pub fn metadata_menu(ui: &mut egui::Ui, rules: &mut Vec<MetaRule>) -> bool {
+    let mut remove = None;
+    let mut up = None;
+    egui::Grid::new("metadata_grid").show(ui, |ui| {
+        ui.label("");
+        ui.label("From");
+        ui.label("To");
+        ui.end_row();
+        for (i, r) in rules.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                if ui.small_button("x").on_hover_text("Remove").clicked() {
+                    remove = Some(i);
+                }
+                if ui
+                    .add_enabled(i > 0, egui::Button::new("^").small())
+                    .on_hover_text("Run earlier")
+                    .clicked()
+                {
+                    up = Some(i);
+                }
+            });
+            ui.add(egui::TextEdit::singleline(&mut r.from).hint_text("%(artist,uploader)s"));
+            ui.add(egui::TextEdit::singleline(&mut r.to).hint_text("%(meta_artist)s"));
+            ui.end_row();
+        }
+    });
+    if let Some(i) = remove {
+        rules.remove(i);
+    }
+    if let Some(i) = up {
+        rules.swap(i, i - 1);
+    }
+    ui.small("Later rules win when their source field has a value");
+    let mut save = false;
+    ui.horizontal(|ui| {
+        if ui.button("Add").clicked() {
+            rules.push(rule("", ""));
+        }
+        if ui.button("Defaults").clicked() {
+            *rules = default_rules();
+        }
+        if ui.button("Save").clicked() {
+            save = true;
+            ui.close_menu();
+        }
+    });
+    save
+}
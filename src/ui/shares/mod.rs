@@ -1,6 +1,26 @@
+pub mod album_split;
+pub mod archive;
+pub mod audio_format;
+pub mod cleanup;
 pub mod config;
+pub mod cookies;
+pub mod cover;
 pub mod lang;
+pub mod live;
 pub mod lrclib;
+pub mod lyrics;
+pub mod lyrics_providers;
+pub mod metadata;
 pub mod musicbrainz;
+pub mod musicbrainz_client;
+pub mod network;
 pub mod notify;
+pub mod process;
+pub mod sections;
+pub mod sidecars;
+pub mod source_audio;
+pub mod sponsorblock;
+pub mod subscriptions;
+pub mod subtitles;
+pub mod translate;
 pub mod version_check;
@@ -1,19 +1,321 @@
+use crate::ui::shares::cover;
+use crate::ui::shares::musicbrainz_client::{self as client, LOOKUP_DAYS, SEARCH_DAYS};
+use eframe::egui;
 use lofty::config::WriteOptions;
 use lofty::picture::{MimeType, Picture, PictureType};
 use lofty::prelude::*;
 use lofty::probe::Probe;
-use lofty::tag::Tag;
+use lofty::tag::{ItemValue, Tag, TagItem};
+use serde::{Deserialize, Serialize};
+use std::cmp::{Ordering as CmpOrdering, Reverse};
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use url::form_urlencoded;
 
-use std::error::Error;
-use std::path::Path;
-use std::time::Duration;
-use ureq::Agent;
+const API: &str = "https://musicbrainz.org/ws/2";
+/// Recordings shown in the review.
+pub const REVIEW_SIZE: usize = 5;
+/// A different song scoring this close to the best one makes the match ambiguous.
+const AMBIGUOUS_GAP: i8 = 5;
 
-pub fn musicbrain_work(opt: &Path, similarity_rate: i8) {
-    let mut tagged_file = Probe::open(&opt)
-        .expect("ERROR: Bad path provided!")
-        .read()
-        .expect("ERROR: Failed to read file!");
+pub fn default_auto_accept() -> i8 {
+    95
+}
+
+/// Release types, as ranked by `ReleasePolicy::types`. Secondary types such as
+/// compilation or live win over the primary one.
+const RELEASE_TYPES: [&str; 8] = [
+    "album",
+    "ep",
+    "single",
+    "compilation",
+    "soundtrack",
+    "live",
+    "remix",
+    "other",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DatePreference {
+    Earliest,
+    Latest,
+}
+
+/// Which of a recording's releases to tag with and take the cover from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReleasePolicy {
+    /// Best first, types not listed come last.
+    pub types: Vec<String>,
+    pub prefer_official: bool,
+    /// Comma separated, e.g. "XW, US". XW is worldwide.
+    pub countries: String,
+    pub date: DatePreference,
+    /// Comma separated media formats, e.g. "Digital Media, CD".
+    pub formats: String,
+}
+
+impl Default for ReleasePolicy {
+    fn default() -> Self {
+        Self {
+            types: RELEASE_TYPES.iter().map(|t| t.to_string()).collect(),
+            prefer_official: true,
+            countries: String::new(),
+            date: DatePreference::Earliest,
+            formats: String::new(),
+        }
+    }
+}
+
+fn list(s: &str) -> Vec<String> {
+    s.split(',')
+        .map(|p| p.trim().to_lowercase())
+        .filter(|p| !p.is_empty())
+        .collect()
+}
+
+impl ReleasePolicy {
+    /// Sorts releases best first: status, type, country, format, then date.
+    pub fn rank(&self, releases: &mut [ReleaseInfo]) {
+        let countries = list(&self.countries);
+        let formats = list(&self.formats);
+        let index = |prefs: &[String], value: &str| {
+            prefs
+                .iter()
+                .position(|p| p.eq_ignore_ascii_case(value))
+                .unwrap_or(prefs.len())
+        };
+        let key = |r: &ReleaseInfo| {
+            (
+                self.prefer_official && !r.status.eq_ignore_ascii_case("official"),
+                index(&self.types, &r.kind),
+                index(&countries, r.country.as_deref().unwrap_or_default()),
+                r.formats
+                    .iter()
+                    .map(|f| index(&formats, f))
+                    .min()
+                    .unwrap_or(formats.len()),
+            )
+        };
+        releases.sort_by(|a, b| {
+            key(a).cmp(&key(b)).then_with(|| match (&a.date, &b.date) {
+                (Some(x), Some(y)) if self.date == DatePreference::Earliest => x.cmp(y),
+                (Some(x), Some(y)) => y.cmp(x),
+                // Undated releases last either way.
+                (Some(_), None) => CmpOrdering::Less,
+                (None, Some(_)) => CmpOrdering::Greater,
+                (None, None) => CmpOrdering::Equal,
+            })
+        });
+    }
+}
+
+/// Settings for one lookup, taken from the music job.
+pub struct MusicBrainzJob<'a> {
+    /// Below this nothing is written.
+    pub threshold: i8,
+    /// With `review`, matches below this (or ambiguous ones) wait for the user.
+    pub auto_accept: i8,
+    pub review: bool,
+    pub policy: &'a ReleasePolicy,
+}
+
+/// One recording from the search, with the releases it appears on.
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub recording_id: String,
+    pub score: i8,
+    pub title: String,
+    pub artist: String,
+    pub releases: Vec<ReleaseInfo>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ReleaseInfo {
+    pub id: String,
+    pub title: String,
+    pub date: Option<String>,
+    pub country: Option<String>,
+    pub status: String,
+    /// Lowercase, one of `RELEASE_TYPES` or empty.
+    pub kind: String,
+    pub formats: Vec<String>,
+}
+
+impl From<Release> for ReleaseInfo {
+    fn from(r: Release) -> Self {
+        let group = r.release_group.as_ref();
+        let kind = group
+            .and_then(|g| g.secondary_types.first().or(g.primary_type.as_ref()))
+            .map(|t| t.to_lowercase())
+            .unwrap_or_default();
+        Self {
+            formats: r
+                .media
+                .iter()
+                .flatten()
+                .filter_map(|m| m.format.clone())
+                .collect(),
+            status: r.status.unwrap_or_default(),
+            kind,
+            id: r.id,
+            title: r.title,
+            date: r.date.filter(|d| !d.is_empty()),
+            country: r.country,
+        }
+    }
+}
+
+/// What gets written: a recording, on a given release if one was picked, with
+/// the title, artist and album as edited in the review.
+#[derive(Debug, Clone)]
+pub struct Choice {
+    pub recording_id: String,
+    pub release_id: Option<String>,
+    pub title: String,
+    pub artist: String,
+    pub album: Option<String>,
+}
+
+impl Candidate {
+    pub fn choice(&self, release: Option<&ReleaseInfo>) -> Choice {
+        Choice {
+            recording_id: self.recording_id.clone(),
+            release_id: release.map(|r| r.id.clone()),
+            title: self.title.clone(),
+            artist: self.artist.clone(),
+            album: release.map(|r| r.title.clone()),
+        }
+    }
+}
+
+/// A track waiting for the user to pick a match.
+#[derive(Debug, Clone)]
+pub struct Pending {
+    pub file: PathBuf,
+    pub title: String,
+    pub artist: String,
+    pub candidates: Vec<Candidate>,
+}
+
+static PENDING: Mutex<Vec<Pending>> = Mutex::new(Vec::new());
+// Set by the app window. Headless syncs have nobody to ask and fall back to the threshold.
+static REVIEWER: AtomicBool = AtomicBool::new(false);
+
+pub fn enable_review() {
+    REVIEWER.store(true, Ordering::Relaxed);
+}
+
+/// Tracks queued since the last call.
+pub fn take_pending() -> Vec<Pending> {
+    match PENDING.lock() {
+        Ok(mut pending) => mem::take(&mut *pending),
+        Err(_) => Vec::new(),
+    }
+}
+
+pub fn musicbrain_work(opt: &Path, job: &MusicBrainzJob) {
+    let (title, artist) = match Probe::open(opt).and_then(|p| p.read()) {
+        Ok(tagged_file) => {
+            let tag = tagged_file.primary_tag().or(tagged_file.first_tag());
+            (
+                tag.and_then(|t| t.title()).unwrap_or_default().to_string(),
+                tag.and_then(|t| t.artist()).unwrap_or_default().to_string(),
+            )
+        }
+        Err(e) => {
+            println!("musicbrainz: Fail to read {opt:?} {e}");
+            return;
+        }
+    };
+    if title.trim().is_empty() {
+        println!("musicbrainz: No title in {opt:?}");
+        return;
+    }
+    let candidates = match search(&title, &artist, job.policy) {
+        Ok(candidates) => candidates,
+        Err(e) => {
+            println!("musicbrainz: {e}");
+            return;
+        }
+    };
+    let Some(best) = candidates.first() else {
+        println!("musicbrainz: No match for {title}");
+        return;
+    };
+    if job.review && REVIEWER.load(Ordering::Relaxed) && needs_review(&candidates, job.auto_accept)
+    {
+        println!("musicbrainz: {opt:?} waits for review");
+        if let Ok(mut pending) = PENDING.lock() {
+            pending.push(Pending {
+                file: opt.to_path_buf(),
+                title,
+                artist,
+                candidates,
+            });
+        }
+        return;
+    }
+    if best.score > job.threshold
+        && let Err(e) = apply(opt, &best.choice(best.releases.first()), job.policy)
+    {
+        println!("musicbrainz: {e}");
+    }
+}
+
+fn needs_review(candidates: &[Candidate], auto_accept: i8) -> bool {
+    let best = &candidates[0];
+    let same = |a: &str, b: &str| a.trim().to_lowercase() == b.trim().to_lowercase();
+    best.score < auto_accept
+        || candidates[1..].iter().any(|c| {
+            c.score + AMBIGUOUS_GAP >= best.score
+                && !(same(&c.title, &best.title) && same(&c.artist, &best.artist))
+        })
+}
+
+/// The best recordings for a title and artist, highest score first, each with
+/// its releases ranked by the policy.
+pub fn search(title: &str, artist: &str, policy: &ReleasePolicy) -> Result<Vec<Candidate>, String> {
+    let title: String = form_urlencoded::byte_serialize(title.as_bytes()).collect();
+    let mut query = format!("{API}/recording?query={title}");
+    if !artist.trim().is_empty() {
+        let artist: String = form_urlencoded::byte_serialize(artist.as_bytes()).collect();
+        query.push_str(&format!("%20AND%20artist:{artist}"));
+    }
+    query.push_str(&format!("&limit={REVIEW_SIZE}&fmt=json"));
+    let resp = client::get_json::<ApiResponseMusicBrainz>(&query, SEARCH_DAYS)?;
+    Ok(resp
+        .recordings
+        .into_iter()
+        .map(|r| {
+            let mut releases: Vec<ReleaseInfo> = r
+                .releases
+                .unwrap_or_default()
+                .into_iter()
+                .map(ReleaseInfo::from)
+                .collect();
+            policy.rank(&mut releases);
+            Candidate {
+                artist: credit_name(r.artist_credit.as_deref().unwrap_or_default()),
+                recording_id: r.id,
+                score: r.score,
+                title: r.title,
+                releases,
+            }
+        })
+        .collect())
+}
+
+/// Writes the chosen recording into the file: title, artist and album from the
+/// choice; dates, label, disc and track number, genres and MusicBrainz IDs from
+/// the recording and release lookups; and the release front cover.
+pub fn apply(opt: &Path, choice: &Choice, policy: &ReleasePolicy) -> Result<(), String> {
+    let mut tagged_file = Probe::open(opt)
+        .and_then(|p| p.read())
+        .map_err(|e| e.to_string())?;
 
     let tag = match tagged_file.primary_tag_mut() {
         Some(primary_tag) => primary_tag,
@@ -30,164 +332,228 @@ pub fn musicbrain_work(opt: &Path, similarity_rate: i8) {
             }
         }
     };
-    use url::form_urlencoded;
+    println!("{}", choice.recording_id);
+    println!("{}", choice.title);
+    tag.set_title(choice.title.clone());
+    if !choice.artist.trim().is_empty() {
+        tag.set_artist(choice.artist.clone());
+    }
+    if let Some(album) = &choice.album {
+        tag.set_album(album.clone());
+    }
 
-    let artist = tag.artist().unwrap();
-    let title = tag.title().unwrap();
-    let artist: String = form_urlencoded::byte_serialize(artist.as_bytes()).collect();
-    let title: String = form_urlencoded::byte_serialize(title.as_bytes()).collect();
-    let query = format!(
-        "https://musicbrainz.org/ws/2/recording?query={}%20AND%20artist:{}&fmt=json",
-        title, artist
-    );
-    println!("{query}");
-    let _ = fetch_musicbrainzapi(&query, opt, similarity_rate, tag);
-}
-fn fetch_musicbrainzapi(
-    q: &str,
-    opt: &Path,
-    similarity_rate: i8,
-    tag: &mut Tag,
-) -> Result<(), Box<dyn Error>> {
-    let config = Agent::config_builder()
-        .timeout_global(Some(Duration::from_secs(5)))
-        .build();
-
-    let agent: Agent = config.into();
-    let re = agent
-        .get(q)
-        .header("User-Agent", "Azulbox (https://github.com/musdx/azul-box)")
-        .call()?
-        .body_mut()
-        .read_json::<ApiResponseMusicBrainz>();
-    match re {
-        Ok(resp) => {
-            if !resp.recordings.is_empty() && (resp.recordings[0].score > similarity_rate) {
-                let record = resp.recordings[0].clone();
-                println!("{}", record.id);
-                println!("{}", record.title);
-                tag.set_title(record.title);
-                let query_with_id = format!(
-                    "https://musicbrainz.org/ws/2/recording/{}?inc=artist-credits+isrcs+releases+release-groups+discids&fmt=json",
-                    record.id
-                );
-                let re_for_id = agent
-                    .get(query_with_id)
-                    .header("User-Agent", "Azulbox (https://github.com/musdx/azul-box)")
-                    .call();
-                match re_for_id {
-                    Ok(mut re) => {
-                        match re.body_mut().read_json::<IDAPI>() {
-                            Ok(data) => {
-                                if let Some(artists) = data.artist_credit {
-                                    println!("{}", artists[0].name);
-                                    tag.set_artist(artists[0].name.clone());
-                                }
-                                if let Some(releases) = data.releases {
-                                    if !releases.is_empty() {
-                                        let release_id = &releases[0].id;
-                                        if let Some(date) = &releases[0].date {
-                                            let years = &date.split("-").next().unwrap();
-                                            let year: u32 = years.parse::<u32>().unwrap();
-                                            tag.set_year(year);
-                                            tag.insert_text(ItemKey::ReleaseDate, date.clone());
-                                        }
-                                        tag.set_album(releases[0].title.clone());
-                                        if let Some(media) = &releases[0].media {
-                                            tag.set_disk(media[0].position);
-                                            tag.set_track(media[0].position);
-                                            tag.set_track_total(media[0].track_count);
-                                            tag.set_disk_total(media[0].track_count);
-                                        }
-
-                                        println!("{release_id}");
-                                        let que = format!(
-                                            "https://coverartarchive.org/release/{}",
-                                            release_id
-                                        );
-                                        println!("{que}");
-                                        let res = agent
-                                            .get(que)
-                                            .header(
-                                                "User-Agent",
-                                                "Azulbox (https://github.com/musdx/azul-box)",
-                                            )
-                                            .call();
-                                        match res {
-                                            Ok(mut awnser) => {
-                                                let succes_re = awnser
-                                                    .body_mut()
-                                                    .read_json::<ApiResponseCover>();
-                                                match succes_re {
-                                                    Ok(callfocover) => {
-                                                        println!("Cover??");
-                                                        if let Some(images) = callfocover.images {
-                                                            println!("{}", images[0].image);
-                                                            let img_req = agent
-                                                                .get(&images[0].image)
-                                                                .header(
-                                                                    "User-Agent",
-                                                                    "Azulbox (https://github.com/musdx/azul-box)",
-                                                                )
-                                                                .call()
-                                                                .expect("did load pic");
-                                                            let data: Vec<u8> = img_req
-                                                                .into_body()
-                                                                .read_to_vec()?;
-
-                                                            let picture = Picture::new_unchecked(
-                                                                PictureType::CoverFront,
-                                                                Some(MimeType::Jpeg),
-                                                                None,
-                                                                data,
-                                                            );
-                                                            println!("Cover mostly work");
-                                                            if tag.picture_count() > 0 {
-                                                                tag.remove_picture(0);
-                                                            }
-                                                            tag.push_picture(picture);
-                                                        };
-                                                    }
-                                                    Err(e) => {
-                                                        println!("{e}");
-                                                        println!("Cover fail")
-                                                    }
-                                                }
-                                            }
-                                            Err(e) => {
-                                                println!("{e}");
-                                                println!("request cover fail");
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                            Err(e) => {
-                                println!("Fail to read data from json with id code: {e}")
-                            }
-                        };
-                    }
-                    Err(e) => {
-                        println!("Fail to request with ID of record code: {e}");
-                    }
-                }
+    let recording = lookup::<IdApi>(&format!(
+        "{API}/recording/{}?inc=artist-credits+isrcs+releases+release-groups+media+genres&fmt=json",
+        choice.recording_id
+    ));
+    // The picked release first, then the others as the policy ranks them.
+    let mut ranked: Vec<ReleaseInfo> = recording
+        .as_ref()
+        .and_then(|r| r.releases.clone())
+        .unwrap_or_default()
+        .into_iter()
+        .map(ReleaseInfo::from)
+        .collect();
+    policy.rank(&mut ranked);
+    if let Some(id) = &choice.release_id {
+        ranked.sort_by_key(|r| &r.id != id);
+    }
+    let release_id = choice
+        .release_id
+        .clone()
+        .or_else(|| ranked.first().map(|r| r.id.clone()));
+    let release = release_id.and_then(|id| {
+        lookup::<Release>(&format!(
+            "{API}/release/{id}?inc=artist-credits+labels+recordings+release-groups+genres&fmt=json"
+        ))
+    });
+
+    tag.insert_text(ItemKey::MusicBrainzRecordingId, choice.recording_id.clone());
+    let mut genres = Vec::new();
+    if let Some(recording) = &recording {
+        if let Some(isrc) = recording.isrcs.first() {
+            tag.insert_text(ItemKey::Isrc, isrc.clone());
+        }
+        set_all(
+            tag,
+            ItemKey::MusicBrainzArtistId,
+            artist_ids(recording.artist_credit.as_deref().unwrap_or_default()),
+        );
+        genres = top_genres(&recording.genres);
+    }
+    if let Some(release) = &release {
+        println!("{}", release.id);
+        write_release(tag, release, choice);
+        if genres.is_empty() {
+            genres = top_genres(&release.genres);
+        }
+        if genres.is_empty()
+            && let Some(group) = &release.release_group
+        {
+            genres = top_genres(&group.genres);
+        }
+        // Not every release has art, fall back to the next best ones.
+        let cover = std::iter::once(release.id.as_str())
+            .chain(
+                ranked
+                    .iter()
+                    .map(|r| r.id.as_str())
+                    .filter(|id| *id != release.id),
+            )
+            .take(3)
+            .find_map(front_cover);
+        if let Some(data) = cover {
+            let picture = Picture::new_unchecked(
+                PictureType::CoverFront,
+                cover::mime_of(&data).or(Some(MimeType::Jpeg)),
+                None,
+                data,
+            );
+            if tag.picture_count() > 0 {
+                tag.remove_picture(0);
             }
+            tag.push_picture(picture);
+        }
+    }
+    if !genres.is_empty() {
+        set_all(tag, ItemKey::Genre, genres);
+    }
+    tag.save_to_path(opt, WriteOptions::default())
+        .map_err(|e| e.to_string())
+}
+
+fn lookup<T: serde::de::DeserializeOwned>(query: &str) -> Option<T> {
+    match client::get_json::<T>(query, LOOKUP_DAYS) {
+        Ok(data) => Some(data),
+        Err(e) => {
+            println!("musicbrainz: Fail to look up {e}");
+            None
+        }
+    }
+}
+
+// Album, dates, label and the track's place on the release, found by looking
+// for the recording among the release's tracks.
+fn write_release(tag: &mut Tag, release: &Release, choice: &Choice) {
+    if choice.album.is_none() {
+        tag.set_album(release.title.clone());
+    }
+    tag.insert_text(ItemKey::MusicBrainzReleaseId, release.id.clone());
+    if let Some(date) = &release.date {
+        if let Some(year) = date.split('-').next().and_then(|y| y.parse().ok()) {
+            tag.set_year(year);
+        }
+        tag.insert_text(ItemKey::ReleaseDate, date.clone());
+    }
+    let credits = release.artist_credit.as_deref().unwrap_or_default();
+    if !credits.is_empty() {
+        tag.insert_text(ItemKey::AlbumArtist, credit_name(credits));
+        set_all(
+            tag,
+            ItemKey::MusicBrainzReleaseArtistId,
+            artist_ids(credits),
+        );
+    }
+    if let Some(group) = &release.release_group {
+        tag.insert_text(ItemKey::MusicBrainzReleaseGroupId, group.id.clone());
+        if let Some(date) = &group.first_release_date
+            && !date.is_empty()
+        {
+            tag.insert_text(ItemKey::OriginalReleaseDate, date.clone());
+        }
+    }
+    let labels = release.label_info.as_deref().unwrap_or_default();
+    if let Some(label) = labels.iter().find_map(|l| l.label.as_ref()) {
+        tag.insert_text(ItemKey::Label, label.name.clone());
+    }
+    if let Some(catalog) = labels.iter().find_map(|l| l.catalog_number.clone()) {
+        tag.insert_text(ItemKey::CatalogNumber, catalog);
+    }
+    let media = release.media.as_deref().unwrap_or_default();
+    let found = media.iter().find_map(|m| {
+        m.tracks
+            .iter()
+            .find(|t| {
+                t.recording
+                    .as_ref()
+                    .is_some_and(|r| r.id == choice.recording_id)
+            })
+            .map(|t| (m, t))
+    });
+    match found {
+        Some((medium, track)) => {
+            tag.set_track(track.position);
+            tag.set_track_total(medium.track_count);
+            tag.set_disk(medium.position);
+            tag.set_disk_total(media.len() as u32);
+            tag.insert_text(ItemKey::MusicBrainzTrackId, track.id.clone());
         }
+        None => println!("musicbrainz: Recording not found on release {}", release.id),
+    }
+}
+
+fn set_all(tag: &mut Tag, key: ItemKey, values: Vec<String>) {
+    tag.remove_key(&key);
+    for value in values {
+        tag.push(TagItem::new(key.clone(), ItemValue::Text(value)));
+    }
+}
+
+fn artist_ids(credits: &[ArtistCredit]) -> Vec<String> {
+    credits
+        .iter()
+        .filter_map(|c| c.artist.as_ref().map(|a| a.id.clone()))
+        .collect()
+}
+
+// Most voted first, a few at most.
+fn top_genres(genres: &[Genre]) -> Vec<String> {
+    let mut genres = genres.to_vec();
+    genres.sort_by_key(|g| Reverse(g.count));
+    genres.into_iter().take(3).map(|g| g.name).collect()
+}
+
+fn front_cover(release_id: &str) -> Option<Vec<u8>> {
+    let que = format!("https://coverartarchive.org/release/{release_id}");
+    let images = match client::get_json::<ApiResponseCover>(&que, LOOKUP_DAYS) {
+        Ok(cover) => cover.images?,
         Err(e) => {
-            println!("{e:?}");
+            println!("musicbrainz: Cover fail {e}");
+            return None;
         }
     };
-    println!("Work");
-    tag.save_to_path(opt, WriteOptions::default())
-        .expect("ERROR: Failed to write the tag!");
-    Ok(())
+    let image = images.iter().find(|i| i.front).or(images.first())?;
+    match client::get(&image.image, LOOKUP_DAYS) {
+        Ok(data) => Some(data),
+        Err(e) => {
+            println!("musicbrainz: Fail to load cover {e}");
+            None
+        }
+    }
+}
+
+/// Small front cover for the review list.
+pub fn thumbnail(release_id: &str) -> Option<Vec<u8>> {
+    let url = format!("https://coverartarchive.org/release/{release_id}/front-250");
+    client::get(&url, LOOKUP_DAYS).ok()
+}
+
+fn credit_name(credits: &[ArtistCredit]) -> String {
+    credits
+        .iter()
+        .map(|c| format!("{}{}", c.name, c.joinphrase))
+        .collect()
 }
-use serde::Deserialize;
+
 #[derive(Debug, Deserialize)]
-struct IDAPI {
+struct IdApi {
+    #[serde(default)]
+    isrcs: Vec<String>,
     #[serde(rename = "artist-credit")]
     artist_credit: Option<Vec<ArtistCredit>>,
     releases: Option<Vec<Release>>,
+    #[serde(default)]
+    genres: Vec<Genre>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -197,6 +563,8 @@ struct ApiResponseCover {
 #[derive(Debug, Deserialize)]
 struct Image {
     image: String,
+    #[serde(default)]
+    front: bool,
 }
 
 #[derive(Debug, Deserialize)]
@@ -208,10 +576,20 @@ struct Recording {
     id: String,
     score: i8,
     title: String,
+    #[serde(rename = "artist-credit")]
+    artist_credit: Option<Vec<ArtistCredit>>,
+    releases: Option<Vec<Release>>,
 }
 #[derive(Debug, Deserialize, Clone)]
 struct ArtistCredit {
     name: String,
+    #[serde(default)]
+    joinphrase: String,
+    artist: Option<Id>,
+}
+#[derive(Debug, Deserialize, Clone)]
+struct Id {
+    id: String,
 }
 #[derive(Debug, Deserialize, Clone)]
 struct Release {
@@ -219,10 +597,228 @@ struct Release {
     title: String,
     media: Option<Vec<Media>>,
     date: Option<String>,
+    country: Option<String>,
+    status: Option<String>,
+    #[serde(rename = "artist-credit")]
+    artist_credit: Option<Vec<ArtistCredit>>,
+    #[serde(rename = "label-info")]
+    label_info: Option<Vec<LabelInfo>>,
+    #[serde(rename = "release-group")]
+    release_group: Option<ReleaseGroup>,
+    #[serde(default)]
+    genres: Vec<Genre>,
+}
+#[derive(Debug, Deserialize, Clone)]
+struct ReleaseGroup {
+    id: String,
+    #[serde(rename = "first-release-date")]
+    first_release_date: Option<String>,
+    #[serde(rename = "primary-type")]
+    primary_type: Option<String>,
+    #[serde(rename = "secondary-types", default)]
+    secondary_types: Vec<String>,
+    #[serde(default)]
+    genres: Vec<Genre>,
+}
+#[derive(Debug, Deserialize, Clone)]
+struct LabelInfo {
+    #[serde(rename = "catalog-number")]
+    catalog_number: Option<String>,
+    label: Option<Label>,
+}
+#[derive(Debug, Deserialize, Clone)]
+struct Label {
+    name: String,
+}
+#[derive(Debug, Deserialize, Clone)]
+struct Genre {
+    name: String,
+    #[serde(default)]
+    count: u32,
 }
 #[derive(Debug, Deserialize, Clone)]
 struct Media {
     position: u32,
     #[serde(rename = "track-count")]
     track_count: u32,
+    format: Option<String>,
+    // Search results call it `track`, lookups `tracks`.
+    #[serde(default, alias = "track")]
+    tracks: Vec<Track>,
+}
+#[derive(Debug, Deserialize, Clone)]
+struct Track {
+    id: String,
+    position: u32,
+    recording: Option<Id>,
+}
+
+/// Release preferences. Returns true when Save was pressed.
+pub fn release_policy_menu(ui: &mut egui::Ui, policy: &mut ReleasePolicy) -> bool {
+    ui.label("Release types, best first:");
+    let mut up = None;
+    egui::Grid::new("release_types_grid").show(ui, |ui| {
+        for (i, kind) in policy.types.iter().enumerate() {
+            ui.label(kind);
+            if ui
+                .add_enabled(i > 0, egui::Button::new("^").small())
+                .on_hover_text("Prefer")
+                .clicked()
+            {
+                up = Some(i);
+            }
+            ui.end_row();
+        }
+    });
+    if let Some(i) = up {
+        policy.types.swap(i, i - 1);
+    }
+    ui.checkbox(&mut policy.prefer_official, "Prefer official releases");
+    ui.horizontal(|ui| {
+        ui.label("Date: ");
+        ui.radio_value(&mut policy.date, DatePreference::Earliest, "Earliest");
+        ui.radio_value(&mut policy.date, DatePreference::Latest, "Latest");
+    });
+    egui::Grid::new("release_prefs_grid").show(ui, |ui| {
+        ui.label("Countries: ");
+        ui.add(egui::TextEdit::singleline(&mut policy.countries).hint_text("XW, US"));
+        ui.end_row();
+        ui.label("Formats: ");
+        ui.add(egui::TextEdit::singleline(&mut policy.formats).hint_text("Digital Media, CD"));
+        ui.end_row();
+    });
+    let mut save = false;
+    ui.horizontal(|ui| {
+        if ui.button("Defaults").clicked() {
+            *policy = ReleasePolicy::default();
+        }
+        if ui.button("Save").clicked() {
+            save = true;
+            ui.close_menu();
+        }
+    });
+    save
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(score: i8, title: &str, artist: &str) -> Candidate {
+        Candidate {
+            recording_id: String::new(),
+            score,
+            title: title.to_string(),
+            artist: artist.to_string(),
+            releases: Vec::new(),
+        }
+    }
+
+    fn release(
+        id: &str,
+        status: &str,
+        kind: &str,
+        country: &str,
+        date: Option<&str>,
+    ) -> ReleaseInfo {
+        ReleaseInfo {
+            id: id.to_string(),
+            title: String::new(),
+            date: date.map(str::to_string),
+            country: Some(country.to_string()).filter(|c| !c.is_empty()),
+            status: status.to_string(),
+            kind: kind.to_string(),
+            formats: Vec::new(),
+        }
+    }
+
+    fn ids(releases: &[ReleaseInfo]) -> Vec<&str> {
+        releases.iter().map(|r| r.id.as_str()).collect()
+    }
+
+    #[test]
+    fn rank_prefers_official_then_type() {
+        let mut releases = vec![
+            release("bootleg-album", "Bootleg", "album", "", Some("2000")),
+            release("single", "Official", "single", "", Some("2000")),
+            release("album", "Official", "album", "", Some("2001")),
+        ];
+        ReleasePolicy::default().rank(&mut releases);
+        assert_eq!(ids(&releases), ["album", "single", "bootleg-album"]);
+        let policy = ReleasePolicy {
+            prefer_official: false,
+            ..ReleasePolicy::default()
+        };
+        policy.rank(&mut releases);
+        assert_eq!(ids(&releases), ["bootleg-album", "album", "single"]);
+    }
+
+    #[test]
+    fn rank_by_country_and_format() {
+        let mut releases = vec![
+            release("gb", "Official", "album", "GB", None),
+            release("us-cd", "Official", "album", "US", None),
+            release("us-digital", "Official", "album", "US", None),
+            release("xw", "Official", "album", "XW", None),
+        ];
+        releases[1].formats = vec!["CD".to_string()];
+        releases[2].formats = vec!["Vinyl".to_string(), "Digital Media".to_string()];
+        let policy = ReleasePolicy {
+            countries: "xw, us".to_string(),
+            formats: "Digital Media, CD".to_string(),
+            ..ReleasePolicy::default()
+        };
+        policy.rank(&mut releases);
+        assert_eq!(ids(&releases), ["xw", "us-digital", "us-cd", "gb"]);
+    }
+
+    #[test]
+    fn rank_by_date_with_undated_last() {
+        let mut releases = vec![
+            release("undated", "Official", "album", "", None),
+            release("late", "Official", "album", "", Some("2010-05")),
+            release("early", "Official", "album", "", Some("1999")),
+        ];
+        ReleasePolicy::default().rank(&mut releases);
+        assert_eq!(ids(&releases), ["early", "late", "undated"]);
+        let policy = ReleasePolicy {
+            date: DatePreference::Latest,
+            ..ReleasePolicy::default()
+        };
+        policy.rank(&mut releases);
+        assert_eq!(ids(&releases), ["late", "early", "undated"]);
+    }
+
+    #[test]
+    fn confident_unique_match_is_taken() {
+        let candidates = [
+            candidate(100, "Song", "Band"),
+            candidate(80, "Other", "Band"),
+        ];
+        assert!(!needs_review(&candidates, 95));
+        assert!(!needs_review(&candidates[..1], 95));
+    }
+
+    #[test]
+    fn low_score_needs_review() {
+        assert!(needs_review(&[candidate(90, "Song", "Band")], 95));
+    }
+
+    #[test]
+    fn close_different_song_needs_review() {
+        let candidates = [
+            candidate(100, "Song", "Band"),
+            candidate(95, "Song", "Cover Band"),
+        ];
+        assert!(needs_review(&candidates, 95));
+    }
+
+    #[test]
+    fn close_same_song_on_other_release_is_fine() {
+        let candidates = [
+            candidate(100, "Song", "Band"),
+            candidate(100, " song ", "BAND"),
+        ];
+        assert!(!needs_review(&candidates, 95));
+    }
 }
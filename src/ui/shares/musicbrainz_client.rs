@@ -0,0 +1,161 @@
+use crate::ui::shares::network;
+use serde::de::DeserializeOwned;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Recording and release lookups and cover art rarely change.
+pub const LOOKUP_DAYS: u64 = 30;
+/// Searches go stale sooner, new releases show up in them.
+pub const SEARCH_DAYS: u64 = 7;
+/// MusicBrainz allows one request per second per client.
+const INTERVAL: Duration = Duration::from_secs(1);
+
+// When the last request to musicbrainz.org went out. Held while waiting, so
+// parallel downloads line up behind each other.
+static LAST: Mutex<Option<Instant>> = Mutex::new(None);
+
+fn wait_turn() {
+    let mut last = LAST.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(at) = *last {
+        let since = at.elapsed();
+        if since < INTERVAL {
+            thread::sleep(INTERVAL - since);
+        }
+    }
+    *last = Some(Instant::now());
+}
+
+/// `$XDG_CACHE_HOME/AzulBox/musicbrainz`
+pub fn cache_dir() -> Option<PathBuf> {
+    dirs::cache_dir().map(|d| d.join("AzulBox").join("musicbrainz"))
+}
+
+// FNV-1a, stable across Rust versions unlike the std hasher.
+fn cache_path(url: &str) -> Option<PathBuf> {
+    let hash = url.bytes().fold(0xcbf29ce484222325u64, |h, b| {
+        (h ^ u64::from(b)).wrapping_mul(0x100000001b3)
+    });
+    Some(cache_dir()?.join(format!("{hash:016x}")))
+}
+
+fn fresh(path: &PathBuf, days: u64) -> Option<Vec<u8>> {
+    let age = fs::metadata(path).ok()?.modified().ok()?.elapsed().ok()?;
+    if age > Duration::from_secs(days * 86400) {
+        return None;
+    }
+    fs::read(path).ok()
+}
+
+fn store(path: &PathBuf, data: &[u8]) {
+    if let Some(dir) = path.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+    if let Err(e) = fs::write(path, data) {
+        println!("musicbrainz: Fail to cache {path:?} {e}");
+    }
+}
+
+/// GET through the cache, entries older than `days` are fetched again.
+/// musicbrainz.org requests wait their turn before every attempt. A 404 is cached as an empty
+/// entry so a release without cover art isn't asked for again.
+pub fn get(url: &str, days: u64) -> Result<Vec<u8>, String> {
+    let path = cache_path(url);
+    if let Some(data) = path.as_ref().and_then(|p| fresh(p, days)) {
+        return if data.is_empty() {
+            Err(format!("{url} not found (cached)"))
+        } else {
+            Ok(data)
+        };
+    }
+    println!("{url}");
+    let resp = if url.contains("musicbrainz.org/") {
+        network::get_paced(url, wait_turn)
+    } else {
+        network::get(url)
+    };
+    let (data, result) = match resp.and_then(|r| r.into_body().read_to_vec()) {
+        Ok(data) => (Some(data.clone()), Ok(data)),
+        Err(ureq::Error::StatusCode(404)) => (Some(Vec::new()), Err(format!("{url} not found"))),
+        Err(e) => (None, Err(e.to_string())),
+    };
+    if let (Some(path), Some(data)) = (&path, data) {
+        store(path, &data);
+    }
+    result
+}
+
+/// `get` for JSON. An entry that doesn't parse is dropped from the cache.
+pub fn get_json<T: DeserializeOwned>(url: &str, days: u64) -> Result<T, String> {
+    let data = get(url, days)?;
+    serde_json::from_slice(&data).map_err(|e| {
+        if let Some(path) = cache_path(url) {
+            let _ = fs::remove_file(path);
+        }
+        e.to_string()
+    })
+}
+
+/// Removes entries past the longest expiry.
+pub fn prune() {
+    let Some(entries) = cache_dir().and_then(|d| fs::read_dir(d).ok()) else {
+        return;
+    };
+    let max = Duration::from_secs(LOOKUP_DAYS * 86400);
+    for entry in entries.flatten() {
+        let old = entry
+            .metadata()
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|t| t.elapsed().ok())
+            .is_some_and(|age| age > max);
+        if old {
+            let _ = fs::remove_file(entry.path());
+        }
+    }
+}
+
+pub fn clear() {
+    if let Some(dir) = cache_dir()
+        && dir.exists()
+        && let Err(e) = fs::remove_dir_all(&dir)
+    {
+        println!("musicbrainz: Fail to clear cache {e}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::time::SystemTime;
+
+    #[test]
+    fn cache_path_is_stable_per_url() {
+        let a = cache_path("https://musicbrainz.org/ws/2/recording/1");
+        assert_eq!(a, cache_path("https://musicbrainz.org/ws/2/recording/1"));
+        assert_ne!(a, cache_path("https://musicbrainz.org/ws/2/recording/2"));
+        // FNV-1a of the empty string is its offset basis.
+        assert!(cache_path("").unwrap().ends_with("cbf29ce484222325"));
+    }
+
+    #[test]
+    fn fresh_expires_by_age() {
+        let path = std::env::temp_dir().join(format!("azulbox-mb-test-{}", std::process::id()));
+        fs::write(&path, b"data").unwrap();
+        assert_eq!(fresh(&path, 1), Some(b"data".to_vec()));
+        let old = SystemTime::now() - Duration::from_secs(2 * 86400);
+        File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(old)
+            .unwrap();
+        assert_eq!(fresh(&path, 1), None);
+        assert_eq!(fresh(&path, 3), Some(b"data".to_vec()));
+        let _ = fs::remove_file(&path);
+        assert_eq!(fresh(&path, 3), None);
+    }
+}
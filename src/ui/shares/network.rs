@@ -0,0 +1,237 @@
+use crate::ui::shares::config;
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+use std::process::Command;
+use std::sync::{LazyLock, Mutex};
+use std::thread;
+use std::time::Duration;
+use ureq::http::Response;
+use ureq::{Agent, Body, Proxy};
+
+/// MusicBrainz asks for `name/version ( contact )`.
+pub const USER_AGENT: &str = concat!(
+    "Azulbox/",
+    env!("CARGO_PKG_VERSION"),
+    " ( https://github.com/musdx/azul-box )"
+);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Network {
+    /// http://host:port, socks5://host:port or empty for none.
+    pub proxy: String,
+    /// yt-dlp `--limit-rate` value, e.g. "2M". Empty for unlimited.
+    pub limit_rate: String,
+    pub retries: u32,
+    /// First wait between retries, doubled after each failed attempt.
+    pub backoff_secs: u64,
+    pub timeout_secs: u64,
+    /// Netscape cookies.txt used by yt-dlp when no cookie profile matches the link.
+    pub cookies: String,
+}
+
+impl Default for Network {
+    fn default() -> Self {
+        Self {
+            proxy: String::new(),
+            limit_rate: String::new(),
+            retries: 3,
+            backoff_secs: 1,
+            timeout_secs: 15,
+            cookies: String::new(),
+        }
+    }
+}
+
+// Read from the config once, then replaced by `set` when the settings are saved.
+static CURRENT: LazyLock<Mutex<(Network, Agent)>> = LazyLock::new(|| {
+    let net = config::load_config(&config::get_config_file_path())
+        .map(|c| c.network)
+        .unwrap_or_default();
+    let agent = build_agent(&net);
+    Mutex::new((net, agent))
+});
+
+fn build_agent(net: &Network) -> Agent {
+    let mut builder = Agent::config_builder()
+        .user_agent(USER_AGENT)
+        .timeout_global(Some(Duration::from_secs(net.timeout_secs.max(1))));
+    // Without a proxy of our own ureq keeps the one from HTTP(S)_PROXY.
+    if !net.proxy.trim().is_empty() {
+        match Proxy::new(net.proxy.trim()) {
+            Ok(p) => builder = builder.proxy(Some(p)),
+            Err(e) => eprintln!("network: Bad proxy {e}"),
+        }
+    }
+    builder.build().into()
+}
+
+pub fn settings() -> Network {
+    CURRENT.lock().unwrap_or_else(|e| e.into_inner()).0.clone()
+}
+
+/// Swaps in saved settings and the agent built from them.
+pub fn set(net: Network) {
+    let agent = build_agent(&net);
+    *CURRENT.lock().unwrap_or_else(|e| e.into_inner()) = (net, agent);
+}
+
+/// The one agent every HTTP integration goes through.
+pub fn agent() -> (Network, Agent) {
+    CURRENT.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+fn retryable(e: &ureq::Error) -> bool {
+    match e {
+        ureq::Error::StatusCode(code) => *code == 429 || *code >= 500,
+        ureq::Error::Io(_)
+        | ureq::Error::Timeout(_)
+        | ureq::Error::ConnectionFailed
+        | ureq::Error::HostNotFound
+        | ureq::Error::BodyStalled => true,
+        _ => false,
+    }
+}
+
+// Runs a request with the shared agent, retrying network errors, 429 and 5xx with backoff.
+fn call(
+    send: impl Fn(&Agent) -> Result<Response<Body>, ureq::Error>,
+) -> Result<Response<Body>, ureq::Error> {
+    let (net, agent) = agent();
+    let mut wait = Duration::from_secs(net.backoff_secs);
+    let mut attempt = 0;
+    loop {
+        match send(&agent) {
+            Ok(resp) => return Ok(resp),
+            Err(e) if attempt < net.retries && retryable(&e) => {
+                attempt += 1;
+                println!("network: {e}, retry {attempt}/{} in {wait:?}", net.retries);
+                thread::sleep(wait);
+                wait *= 2;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// GET with the shared agent, retrying network errors, 429 and 5xx with backoff.
+pub fn get_with(url: &str, headers: &[(&str, &str)]) -> Result<Response<Body>, ureq::Error> {
+    call(|agent| {
+        let mut req = agent.get(url);
+        for (name, value) in headers {
+            req = req.header(*name, *value);
+        }
+        req.call()
+    })
+}
+
+pub fn get(url: &str) -> Result<Response<Body>, ureq::Error> {
+    get_with(url, &[])
+}
+
+/// `get` that runs `before` ahead of every attempt, retries included, for
+/// hosts with a rate limit.
+pub fn get_paced(url: &str, before: impl Fn()) -> Result<Response<Body>, ureq::Error> {
+    call(|agent| {
+        before();
+        agent.get(url).call()
+    })
+}
+
+/// POST a JSON body, with the same retries as `get_with`.
+pub fn post_json(url: &str, body: &impl Serialize) -> Result<Response<Body>, ureq::Error> {
+    call(|agent| agent.post(url).send_json(body))
+}
+
+/// ffmpeg (including the one yt-dlp starts) reads the proxy from the environment.
+pub fn apply_ffmpeg(cmd: &mut Command, net: &Network) {
+    if !net.proxy.trim().is_empty() {
+        cmd.env("http_proxy", net.proxy.trim())
+            .env("https_proxy", net.proxy.trim());
+    }
+}
+
+pub fn apply_yt_dlp(yt: &mut Command, net: &Network) {
+    if !net.proxy.trim().is_empty() {
+        yt.arg("--proxy").arg(net.proxy.trim());
+    }
+    if !net.limit_rate.trim().is_empty() {
+        yt.arg("--limit-rate").arg(net.limit_rate.trim());
+    }
+    // Our defaults are for the HTTP lookups, yt-dlp keeps its own (10 retries,
+    // 20 s timeout) until the user picks a value.
+    let defaults = Network::default();
+    if net.retries != defaults.retries {
+        yt.arg("--retries")
+            .arg(net.retries.to_string())
+            .arg("--fragment-retries")
+            .arg(net.retries.to_string());
+    }
+    if net.timeout_secs != defaults.timeout_secs {
+        yt.arg("--socket-timeout")
+            .arg(net.timeout_secs.max(1).to_string());
+    }
+    yt.arg("--retry-sleep").arg(format!(
+        "exp={}:{}",
+        net.backoff_secs.max(1),
+        net.backoff_secs.max(1) * 32
+    ));
+    apply_ffmpeg(yt, net);
+}
+
+pub fn network_menu(ui: &mut egui::Ui, net: &mut Network) {
+    egui::Grid::new("network_grid").show(ui, |ui| {
+        ui.label("Proxy: ");
+        ui.add(egui::TextEdit::singleline(&mut net.proxy).hint_text("socks5://127.0.0.1:1080"));
+        ui.end_row();
+        ui.label("Bandwidth limit: ")
+            .on_hover_text("yt-dlp only, e.g. 500K or 2M");
+        ui.add(egui::TextEdit::singleline(&mut net.limit_rate).hint_text("unlimited"));
+        ui.end_row();
+        ui.label("Retries: ");
+        ui.add(egui::DragValue::new(&mut net.retries).range(0..=20));
+        ui.end_row();
+        ui.label("Backoff: ");
+        ui.add(
+            egui::DragValue::new(&mut net.backoff_secs)
+                .range(0..=60)
+                .suffix(" s"),
+        );
+        ui.end_row();
+        ui.label("Timeout: ");
+        ui.add(
+            egui::DragValue::new(&mut net.timeout_secs)
+                .range(1..=300)
+                .suffix(" s"),
+        );
+        ui.end_row();
+        ui.label("Cookies: ");
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut net.cookies).hint_text("cookies.txt"));
+            if ui.button("Browse").clicked() {
+                let path = native_dialog::DialogBuilder::file()
+                    .add_filter("Cookies", ["txt"])
+                    .open_single_file()
+                    .show()
+                    .unwrap();
+                if let Some(p) = path {
+                    net.cookies = p.to_string_lossy().into_owned();
+                }
+            }
+        });
+        ui.end_row();
+    });
+    if ui.button("Save").clicked() {
+        let path = config::get_config_file_path();
+        match config::modifier_config(&path, |cfg| cfg.network = net.clone()) {
+            Ok(_) => {
+                set(net.clone());
+                println!("network: Saved")
+            }
+            Err(e) => {
+                eprintln!("network: Fail to save {e}")
+            }
+        }
+        ui.close_menu();
+    }
+}
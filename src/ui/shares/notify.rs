@@ -35,6 +35,14 @@ pub fn notification_done(info: &str) -> Result<(), Box<dyn Error>> {
         .show()?;
     Ok(())
 }
+pub fn notification_summary(body: &str) -> Result<(), Box<dyn Error>> {
+    Notification::new()
+        .summary("Azul Box")
+        .body(body)
+        .icon("azul_box")
+        .show()?;
+    Ok(())
+}
 pub fn notification_fail(info: &str) -> Result<(), Box<dyn Error>> {
     Notification::new()
         .summary("Azul Box")
@@ -0,0 +1,114 @@
+use crate::ui::shares::config::get_config_dir_path;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// The yt-dlp process a download is running, so Cancel stops that one and not
+/// another window's download or a background subscription sync.
+#[derive(Debug, Clone, Default)]
+pub struct Running(Arc<AtomicU32>);
+
+impl Running {
+    /// Like `Command::output`, with the pid remembered while it runs.
+    pub fn output(&self, cmd: &mut Command) -> io::Result<Output> {
+        let child = cmd
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        self.set(child.id());
+        let output = child.wait_with_output();
+        self.clear();
+        output
+    }
+
+    /// For callers that spawn and wait on the child themselves.
+    pub fn set(&self, pid: u32) {
+        self.0.store(pid, Ordering::Relaxed);
+    }
+    pub fn clear(&self) {
+        self.0.store(0, Ordering::Relaxed);
+    }
+
+    /// Kills the process and whatever it started, e.g. ffmpeg.
+    pub fn kill(&self) {
+        let pid = self.0.load(Ordering::Relaxed);
+        if pid == 0 {
+            return;
+        }
+        let _ = Command::new("pkill")
+            .arg("-P")
+            .arg(pid.to_string())
+            .output();
+        let _ = Command::new("kill").arg(pid.to_string()).output();
+    }
+
+    /// SIGINT to the process alone, so yt-dlp can finish and remux what it has.
+    pub fn interrupt(&self) {
+        let pid = self.0.load(Ordering::Relaxed);
+        if pid != 0 {
+            let _ = Command::new("kill")
+                .arg("-INT")
+                .arg(pid.to_string())
+                .output();
+        }
+    }
+}
+
+/// `<name>.lock` in the config directory, removed when dropped. Keeps a headless
+/// `--sync-subscriptions` or `--retry-lyrics` run and the open app from doing the
+/// same job at once and overwriting each other's config writes.
+pub struct FileLock(PathBuf);
+
+impl FileLock {
+    /// None while another live process holds the lock. A lock left behind by a
+    /// process that is gone is taken over.
+    pub fn acquire(name: &str) -> Option<FileLock> {
+        let dir = get_config_dir_path();
+        let _ = fs::create_dir_all(&dir);
+        let path = dir.join(format!("{name}.lock"));
+        for _ in 0..2 {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut file) => {
+                    let _ = write!(file, "{}", std::process::id());
+                    return Some(FileLock(path));
+                }
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                    // An empty file is a lock still being written, not a stale one.
+                    let holder = fs::read_to_string(&path)
+                        .ok()
+                        .and_then(|s| s.trim().parse::<u32>().ok());
+                    match holder {
+                        Some(pid) if !alive(pid) => {
+                            let _ = fs::remove_file(&path);
+                        }
+                        _ => return None,
+                    }
+                }
+                Err(e) => {
+                    eprintln!("process: Fail to lock {path:?} {e}");
+                    return None;
+                }
+            }
+        }
+        None
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+fn alive(pid: u32) -> bool {
+    Command::new("kill")
+        .arg("-0")
+        .arg(pid.to_string())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|s| s.success())
+}
@@ -0,0 +1,115 @@
+use crate::ui::shares::album_split::parse_timestamp;
+use eframe::egui::{self, Color32};
+use std::process::Command;
+
+// Accepts "90", "1:30" or "1:02:03"
+fn seconds(s: &str) -> Option<f64> {
+    let s = s.trim();
+    if s.contains(':') {
+        parse_timestamp(s)
+    } else {
+        s.parse::<f64>().ok().filter(|n| *n >= 0.0)
+    }
+}
+
+/// Reads "1:00-1:30, 2:00:00-2:00:30" into yt-dlp `--download-sections` ranges.
+/// An empty end ("1:00-") means until the end of the video.
+pub fn parse_sections(text: &str) -> Result<Vec<String>, String> {
+    let mut ranges = Vec::new();
+    for part in text.split([',', '\n']).map(|p| p.trim()) {
+        if part.is_empty() {
+            continue;
+        }
+        let (start, end) = part
+            .split_once('-')
+            .ok_or_else(|| format!("\"{part}\" needs start-end"))?;
+        let start_s = seconds(start).ok_or_else(|| format!("Bad start time \"{start}\""))?;
+        if end.trim().is_empty() {
+            ranges.push(format!("*{}-inf", start.trim()));
+            continue;
+        }
+        let end_s = seconds(end).ok_or_else(|| format!("Bad end time \"{end}\""))?;
+        if end_s <= start_s {
+            return Err(format!("\"{part}\" ends before it starts"));
+        }
+        ranges.push(format!("*{}-{}", start.trim(), end.trim()));
+    }
+    Ok(ranges)
+}
+
+pub fn apply(yt: &mut Command, sections: &[String], exact: bool) {
+    for section in sections.iter() {
+        yt.arg("--download-sections").arg(section);
+    }
+    if !sections.is_empty() && exact {
+        yt.arg("--force-keyframes-at-cuts");
+    }
+}
+
+/// Several ranges from one video need different names or they overwrite each other.
+pub fn output_template(base: &str, sections: &[String]) -> String {
+    if sections.is_empty() {
+        base.to_string()
+    } else {
+        base.replace(".%(ext)s", " %(section_start)d-%(section_end)d.%(ext)s")
+    }
+}
+
+/// Time range field with its exact-cut toggle. Returns true when `exact` changed.
+pub fn sections_ui(ui: &mut egui::Ui, text: &mut String, exact: &mut bool) -> bool {
+    let range_label = ui.label("Time range (optional): ");
+    ui.add(
+        egui::TextEdit::singleline(text)
+            .hint_text("1:00-1:30, 2:00:00-2:00:30")
+            .id_salt(range_label.id),
+    );
+    if let Err(e) = parse_sections(text) {
+        ui.colored_label(Color32::LIGHT_RED, e);
+    }
+    if text.trim().is_empty() {
+        return false;
+    }
+    ui.checkbox(exact, "Exact cuts (re-encode at cut points)")
+        .on_hover_text("Off snaps to the nearest keyframes, which is faster but less precise")
+        .changed()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranges() {
+        assert_eq!(
+            parse_sections("1:00-1:30, 2:00:00-2:00:30\n90-100"),
+            Ok(vec![
+                "*1:00-1:30".to_string(),
+                "*2:00:00-2:00:30".to_string(),
+                "*90-100".to_string(),
+            ])
+        );
+        assert_eq!(parse_sections("1:00-"), Ok(vec!["*1:00-inf".to_string()]));
+        assert_eq!(parse_sections("  "), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn bad_ranges() {
+        assert!(parse_sections("1:00").is_err());
+        assert!(parse_sections("x-1:00").is_err());
+        assert!(parse_sections("1:00-y").is_err());
+        assert!(parse_sections("2:00-1:00").is_err());
+        assert!(parse_sections("1:00-1:00").is_err());
+    }
+
+    #[test]
+    fn template() {
+        assert_eq!(
+            output_template("%(title)s.%(ext)s", &[]),
+            "%(title)s.%(ext)s"
+        );
+        assert_eq!(
+            output_template("%(title)s.%(ext)s", &["*1:00-1:30".to_string()]),
+            "%(title)s %(section_start)d-%(section_end)d.%(ext)s"
+        );
+    }
+}
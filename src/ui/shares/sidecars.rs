@@ -0,0 +1,368 @@
+use crate::ui::shares::subtitles;
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Where an artifact ends up: inside the media file, next to it, or both.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Keep {
+    Off,
+    Embed,
+    Sidecar,
+    Both,
+}
+
+impl Keep {
+    pub fn embed(self) -> bool {
+        matches!(self, Keep::Embed | Keep::Both)
+    }
+    pub fn sidecar(self) -> bool {
+        matches!(self, Keep::Sidecar | Keep::Both)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Sidecars {
+    pub enabled: bool,
+    pub info_json: Keep,
+    pub description: Keep,
+    pub thumbnails: Keep,
+    pub subtitles: Keep,
+    /// Also the automatic captions in the video's own language.
+    pub auto_captions: bool,
+    pub comments: Keep,
+    pub chapters: Keep,
+    /// Kodi/Jellyfin `.nfo` next to the video.
+    pub nfo: bool,
+}
+
+impl Default for Sidecars {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            info_json: Keep::Sidecar,
+            description: Keep::Both,
+            thumbnails: Keep::Both,
+            subtitles: Keep::Both,
+            auto_captions: false,
+            comments: Keep::Sidecar,
+            chapters: Keep::Both,
+            nfo: true,
+        }
+    }
+}
+
+impl Sidecars {
+    // Comments, chapter files and the .nfo are made from the info JSON after the download.
+    fn needs_info(&self) -> bool {
+        self.info_json.sidecar() || self.comments.sidecar() || self.chapters.sidecar() || self.nfo
+    }
+}
+
+/// Adds the yt-dlp flags for every artifact. The caller skips its own
+/// `--embed-thumbnail` and subtitle flags while this is enabled.
+/// The returned path is what `finish` reads back.
+pub fn apply(yt: &mut Command, link: &str, sc: &Sidecars) -> PathBuf {
+    let stamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    let printed = std::env::temp_dir().join(format!("azulbox-sidecars-{stamp}.txt"));
+    // The info JSON is named by its own template, e.g. without the section times
+    // of a clip, so take yt-dlp's path for it instead of guessing.
+    yt.arg("--print-to-file")
+        .arg("after_move:%(filepath)s\t%(infojson_filename)s")
+        .arg(&printed);
+    if sc.needs_info() {
+        yt.arg("--write-info-json");
+    }
+    if sc.info_json.embed() {
+        yt.arg("--embed-info-json");
+    }
+    if sc.description.sidecar() {
+        yt.arg("--write-description");
+    }
+    if sc.thumbnails.sidecar() {
+        yt.arg("--write-all-thumbnails");
+    }
+    if sc.thumbnails.embed() {
+        yt.arg("--embed-thumbnail");
+    }
+    if sc.subtitles != Keep::Off {
+        yt.arg("--write-subs")
+            .arg("--sub-langs")
+            .arg(sub_langs(link, sc.auto_captions));
+        if sc.auto_captions {
+            yt.arg("--write-auto-subs");
+        }
+    }
+    if sc.subtitles.embed() {
+        yt.arg("--embed-subs");
+        if !sc.subtitles.sidecar() {
+            yt.arg("--compat-options").arg("no-keep-subs");
+        }
+    }
+    if sc.comments != Keep::Off {
+        yt.arg("--write-comments");
+    }
+    if sc.chapters.embed() {
+        yt.arg("--embed-chapters");
+    } else {
+        yt.arg("--no-embed-chapters");
+    }
+    printed
+}
+
+// "all" with automatic captions pulls every machine translation YouTube offers,
+// so those are narrowed to the manual tracks plus the original-language caption.
+fn sub_langs(link: &str, auto: bool) -> String {
+    let all = "all,-live_chat".to_string();
+    if !auto {
+        return all;
+    }
+    match subtitles::fetch_tracks(link) {
+        Ok(tracks) => {
+            let mut langs: Vec<&str> = tracks
+                .iter()
+                .filter(|t| !t.auto || t.lang.ends_with("-orig"))
+                .map(|t| t.lang.as_str())
+                .collect();
+            langs.dedup();
+            if langs.is_empty() {
+                all
+            } else {
+                langs.join(",")
+            }
+        }
+        Err(e) => {
+            println!("sidecars: Fail to list subtitles {e}");
+            all
+        }
+    }
+}
+
+fn text<'a>(info: &'a Value, keys: &[&str]) -> Option<&'a str> {
+    keys.iter()
+        .find_map(|k| info.get(*k).and_then(|v| v.as_str()))
+        .filter(|s| !s.is_empty())
+}
+
+fn timestamp(seconds: f64) -> String {
+    let s = seconds.max(0.0) as u64;
+    format!("{:02}:{:02}:{:02}", s / 3600, s / 60 % 60, s % 60)
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// A `<movie>` .nfo with the fields Kodi and Jellyfin read.
+fn nfo(info: &Value) -> String {
+    let mut out =
+        String::from("<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n<movie>\n");
+    let mut field = |name: &str, value: &str| {
+        out.push_str(&format!("  <{name}>{}</{name}>\n", escape(value)));
+    };
+    if let Some(title) = text(info, &["title"]) {
+        field("title", title);
+    }
+    if let Some(plot) = text(info, &["description"]) {
+        field("plot", plot);
+    }
+    if let Some(studio) = text(info, &["channel", "uploader"]) {
+        field("studio", studio);
+        field("director", studio);
+    }
+    if let Some(date) = text(info, &["upload_date", "release_date"]).filter(|d| d.len() == 8) {
+        let date = format!("{}-{}-{}", &date[..4], &date[4..6], &date[6..]);
+        field("premiered", &date);
+        field("year", &date[..4]);
+    }
+    if let Some(duration) = info.get("duration").and_then(|v| v.as_f64()) {
+        field("runtime", &((duration / 60.0).round() as u64).to_string());
+    }
+    for key in ["categories", "tags"] {
+        let name = if key == "categories" { "genre" } else { "tag" };
+        for value in info
+            .get(key)
+            .and_then(|v| v.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|v| v.as_str())
+        {
+            field(name, value);
+        }
+    }
+    if let Some(thumb) = text(info, &["thumbnail"]) {
+        field("thumb", thumb);
+    }
+    if let (Some(id), Some(extractor)) = (text(info, &["id"]), text(info, &["extractor_key"])) {
+        out.push_str(&format!(
+            "  <uniqueid type=\"{}\" default=\"true\">{}</uniqueid>\n",
+            escape(&extractor.to_lowercase()),
+            escape(id)
+        ));
+    }
+    out.push_str("</movie>\n");
+    out
+}
+
+/// Writes the sidecars yt-dlp can't (comments, chapters, .nfo) next to every
+/// finished file, then drops the info JSONs unless they were asked for.
+pub fn finish(printed: &Path, sc: &Sidecars) {
+    let content = fs::read_to_string(printed).unwrap_or_default();
+    let _ = fs::remove_file(printed);
+    let mut infos: Vec<PathBuf> = Vec::new();
+    for line in content.lines() {
+        let Some((file, info)) = line.split_once('\t') else {
+            continue;
+        };
+        let file = Path::new(file);
+        let info_path = match (info, file.parent()) {
+            ("NA" | "", _) | (_, None) => file.with_extension("info.json"),
+            (info, Some(dir)) => dir.join(info),
+        };
+        write_sidecars(file, &info_path, sc);
+        if !infos.contains(&info_path) {
+            infos.push(info_path);
+        }
+    }
+    // Clips of one video can share an info JSON, so it goes once all are done.
+    if !sc.info_json.sidecar() {
+        for info_path in infos.iter() {
+            let _ = fs::remove_file(info_path);
+        }
+    }
+}
+
+fn write_sidecars(file: &Path, info_path: &Path, sc: &Sidecars) {
+    let Some(info) = fs::read_to_string(info_path)
+        .ok()
+        .and_then(|s| serde_json::from_str::<Value>(&s).ok())
+    else {
+        if sc.needs_info() {
+            println!("sidecars: No info JSON for {file:?}");
+        }
+        return;
+    };
+
+    if sc.comments.sidecar()
+        && let Some(comments) = info.get("comments").filter(|c| c.is_array())
+    {
+        match serde_json::to_string_pretty(comments) {
+            Ok(s) => {
+                if let Err(e) = fs::write(file.with_extension("comments.json"), s) {
+                    println!("sidecars: Fail to write comments {e}");
+                }
+            }
+            Err(e) => println!("sidecars: Fail to serialize comments {e}"),
+        }
+    }
+
+    if sc.chapters.sidecar() {
+        let chapters: Vec<String> = info
+            .get("chapters")
+            .and_then(|c| c.as_array())
+            .into_iter()
+            .flatten()
+            .map(|c| {
+                format!(
+                    "{} {}",
+                    timestamp(c.get("start_time").and_then(|v| v.as_f64()).unwrap_or(0.0)),
+                    c.get("title").and_then(|v| v.as_str()).unwrap_or("")
+                )
+            })
+            .collect();
+        if !chapters.is_empty()
+            && let Err(e) = fs::write(
+                file.with_extension("chapters.txt"),
+                chapters.join("\n") + "\n",
+            )
+        {
+            println!("sidecars: Fail to write chapters {e}");
+        }
+    }
+
+    if sc.nfo
+        && let Err(e) = fs::write(file.with_extension("nfo"), nfo(&info))
+    {
+        println!("sidecars: Fail to write nfo {e}");
+    }
+}
+
+fn keep_row(ui: &mut egui::Ui, label: &str, keep: &mut Keep, modes: &[Keep]) -> bool {
+    let mut changed = false;
+    ui.label(label);
+    for mode in [Keep::Off, Keep::Embed, Keep::Sidecar, Keep::Both] {
+        let name = match mode {
+            Keep::Off => "Off",
+            Keep::Embed => "Embed",
+            Keep::Sidecar => "Sidecar",
+            Keep::Both => "Both",
+        };
+        if modes.contains(&mode) {
+            changed |= ui.radio_value(keep, mode, name).changed();
+        } else {
+            ui.label("");
+        }
+    }
+    ui.end_row();
+    changed
+}
+
+/// Per-artifact embed/sidecar choice. Returns true when something changed.
+pub fn sidecars_menu(ui: &mut egui::Ui, sc: &mut Sidecars) -> bool {
+    use Keep::*;
+    let mut changed = ui
+        .checkbox(&mut sc.enabled, "Archival mode")
+        .on_hover_text("Keep full metadata with every video")
+        .changed();
+    ui.add_enabled_ui(sc.enabled, |ui| {
+        egui::Grid::new("sidecars_grid").show(ui, |ui| {
+            changed |= keep_row(
+                ui,
+                "Info JSON",
+                &mut sc.info_json,
+                &[Off, Embed, Sidecar, Both],
+            );
+            changed |= keep_row(ui, "Description", &mut sc.description, &[Embed, Both]);
+            changed |= keep_row(
+                ui,
+                "Thumbnails",
+                &mut sc.thumbnails,
+                &[Off, Embed, Sidecar, Both],
+            );
+            changed |= keep_row(
+                ui,
+                "Subtitles",
+                &mut sc.subtitles,
+                &[Off, Embed, Sidecar, Both],
+            );
+            ui.label("");
+            changed |= ui
+                .checkbox(&mut sc.auto_captions, "Auto captions")
+                .on_hover_text("Only the one in the video's language")
+                .changed();
+            ui.end_row();
+            changed |= keep_row(ui, "Comments", &mut sc.comments, &[Off, Sidecar]);
+            changed |= keep_row(
+                ui,
+                "Chapters",
+                &mut sc.chapters,
+                &[Off, Embed, Sidecar, Both],
+            );
+        });
+        ui.small("Embedded info JSON needs MKV");
+        changed |= ui.checkbox(&mut sc.nfo, "Kodi/Jellyfin .nfo").changed();
+    });
+    changed
+}
@@ -0,0 +1,87 @@
+use crate::ui::shares::cookies;
+use crate::ui::shares::network;
+use std::process::Command;
+
+/// The audio stream yt-dlp would pick with `-f bestaudio`.
+#[derive(Debug, Clone)]
+pub struct SourceAudio {
+    pub codec: String,
+    pub abr: Option<f64>,
+    pub ext: String,
+}
+
+impl SourceAudio {
+    pub fn lossless(&self) -> bool {
+        let codec = self.codec.to_lowercase();
+        ["flac", "alac", "wav", "pcm", "aiff", "wavpack"]
+            .iter()
+            .any(|c| codec.starts_with(c))
+    }
+    /// Where "Best / original" puts the stream without transcoding.
+    pub fn container(&self) -> &'static str {
+        let codec = self.codec.to_lowercase();
+        if codec.starts_with("opus") {
+            "OPUS (OGG)"
+        } else if codec.starts_with("mp4a") || codec.starts_with("aac") {
+            "M4A"
+        } else if codec.starts_with("mp3") {
+            "MP3"
+        } else if codec.starts_with("vorbis") {
+            "OGG"
+        } else if codec.starts_with("flac") {
+            "FLAC"
+        } else {
+            "original"
+        }
+    }
+    pub fn describe(&self) -> String {
+        let codec = self.codec.split('.').next().unwrap_or(&self.codec);
+        match self.abr {
+            Some(abr) if abr > 0.0 => format!("{codec} {abr:.0} kbps ({})", self.ext),
+            _ => format!("{codec} ({})", self.ext),
+        }
+    }
+}
+
+fn number(s: &str) -> Option<f64> {
+    s.trim().parse::<f64>().ok()
+}
+
+pub fn probe(link: &str) -> Result<SourceAudio, String> {
+    let mut yt = Command::new("yt-dlp");
+    yt.arg("-f")
+        .arg("bestaudio/best")
+        .arg("--no-playlist")
+        .arg("--playlist-items")
+        .arg("1")
+        .arg("--print")
+        .arg("%(acodec)s\t%(abr)s\t%(ext)s");
+    let net = network::settings();
+    network::apply_yt_dlp(&mut yt, &net);
+    cookies::apply(&mut yt, link, &net);
+    let output = yt.arg(link).output().map_err(|e| e.to_string())?;
+    let out = String::from_utf8_lossy(&output.stdout);
+    let line = out.lines().next().unwrap_or_default();
+    let mut parts = line.split('\t');
+    let codec = parts.next().unwrap_or_default().trim().to_string();
+    if !output.status.success() || codec.is_empty() || codec == "NA" || codec == "none" {
+        let err = String::from_utf8_lossy(&output.stderr);
+        return Err(err
+            .lines()
+            .rfind(|l| l.starts_with("ERROR"))
+            .unwrap_or("Could not read the source audio")
+            .to_string());
+    }
+    Ok(SourceAudio {
+        codec,
+        abr: parts.next().and_then(number),
+        ext: parts.next().unwrap_or_default().trim().to_string(),
+    })
+}
+
+pub enum SourceState {
+    Idle,
+    Loading,
+    Ready(SourceAudio),
+    Failed(String),
+}
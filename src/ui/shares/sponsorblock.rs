@@ -0,0 +1,112 @@
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+use std::process::Command;
+
+pub const DEFAULT_API: &str = "https://sponsor.ajay.app";
+
+// (yt-dlp category, label, can be removed). Highlight and chapter only make sense as marks.
+const CATEGORIES: [(&str, &str, bool); 10] = [
+    ("sponsor", "Sponsor", true),
+    ("intro", "Intro", true),
+    ("outro", "Outro", true),
+    ("selfpromo", "Self promotion", true),
+    ("preview", "Preview", true),
+    ("filler", "Filler", true),
+    ("interaction", "Interaction", true),
+    ("music_offtopic", "Non-music", true),
+    ("poi_highlight", "Highlight", false),
+    ("chapter", "Chapter", false),
+];
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SponsorBlock {
+    pub remove: Vec<String>,
+    pub mark: Vec<String>,
+}
+
+impl SponsorBlock {
+    /// Songs get their non-music parts cut out by default.
+    pub fn music_default() -> Self {
+        Self {
+            remove: vec!["music_offtopic".to_string()],
+            mark: Vec::new(),
+        }
+    }
+}
+
+pub fn default_api() -> String {
+    DEFAULT_API.to_string()
+}
+
+pub fn apply(yt: &mut Command, sb: &SponsorBlock, api: &str) {
+    if sb.remove.is_empty() && sb.mark.is_empty() {
+        return;
+    }
+    if !sb.remove.is_empty() {
+        yt.arg("--sponsorblock-remove").arg(sb.remove.join(","));
+    }
+    if !sb.mark.is_empty() {
+        yt.arg("--sponsorblock-mark").arg(sb.mark.join(","));
+    }
+    if !api.is_empty() && api != DEFAULT_API {
+        yt.arg("--sponsorblock-api").arg(api);
+    }
+}
+
+fn toggle(list: &mut Vec<String>, category: &str, on: bool) {
+    list.retain(|c| c != category);
+    if on {
+        list.push(category.to_string());
+    }
+}
+
+/// Remove/mark checkboxes per category plus the API URL. Returns true when
+/// the categories changed and should be saved; the API is saved through `api_changed`.
+pub fn sponsorblock_menu(
+    ui: &mut egui::Ui,
+    sb: &mut SponsorBlock,
+    api: &mut String,
+    api_changed: &mut bool,
+) -> bool {
+    let mut changed = false;
+    egui::Grid::new("sponsorblock_grid").show(ui, |ui| {
+        ui.label("");
+        ui.label("Remove");
+        ui.label("Mark");
+        ui.end_row();
+        for (category, label, removable) in CATEGORIES {
+            ui.label(label);
+            if removable {
+                let mut remove = sb.remove.iter().any(|c| c == category);
+                if ui.checkbox(&mut remove, "").changed() {
+                    toggle(&mut sb.remove, category, remove);
+                    if remove {
+                        toggle(&mut sb.mark, category, false);
+                    }
+                    changed = true;
+                }
+            } else {
+                ui.label("");
+            }
+            let mut mark = sb.mark.iter().any(|c| c == category);
+            if ui.checkbox(&mut mark, "").changed() {
+                toggle(&mut sb.mark, category, mark);
+                if mark {
+                    toggle(&mut sb.remove, category, false);
+                }
+                changed = true;
+            }
+            ui.end_row();
+        }
+    });
+    ui.separator();
+    let api_label = ui.label("API: ");
+    let response = ui.text_edit_singleline(api).labelled_by(api_label.id);
+    if response.lost_focus() {
+        if api.trim().is_empty() {
+            *api = default_api();
+        }
+        *api_changed = true;
+    }
+    changed
+}
@@ -0,0 +1,126 @@
+use crate::ui::music_dl::{self, MusicJob};
+use crate::ui::shares::archive::{self, ArchiveTool};
+use crate::ui::shares::config::{self, Config, Subscription};
+use crate::ui::shares::notify::notification_summary;
+use crate::ui::shares::process::FileLock;
+use crate::ui::video_dl::{self, VideoJob};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+// A sync running in this process. Other processes are kept out by the lock file.
+static SYNCING: AtomicBool = AtomicBool::new(false);
+
+pub fn is_syncing() -> bool {
+    SYNCING.load(Ordering::Relaxed)
+}
+
+pub fn is_due(sub: &Subscription) -> bool {
+    archive::now().saturating_sub(sub.last_check) >= u64::from(sub.interval_hours) * 3600
+}
+
+/// Downloads whatever is new for one subscription. Returns the number of new entries,
+/// or None when yt-dlp failed.
+pub fn sync_one(configs: &Config, sub: &Subscription) -> Option<usize> {
+    let before = archive::entries(sub.tool).len();
+    let status = match sub.tool {
+        ArchiveTool::Music => music_dl::download(MusicJob::from_config(
+            configs,
+            &sub.url,
+            &sub.directory,
+            sub.audio,
+        )),
+        ArchiveTool::Video => video_dl::download(VideoJob::from_config(
+            configs,
+            &sub.url,
+            &sub.directory,
+            sub.preset,
+        )),
+    };
+    let after = archive::entries(sub.tool).len();
+
+    let path = config::get_config_file_path();
+    match config::modifier_config(&path, |cfg| {
+        for s in cfg.subscriptions.list.iter_mut() {
+            if s.url == sub.url && s.tool == sub.tool {
+                s.last_check = archive::now();
+            }
+        }
+    }) {
+        Ok(_) => {}
+        Err(e) => eprintln!("subscriptions: Fail to save last check {e}"),
+    }
+
+    if status == 2 {
+        Some(after.saturating_sub(before))
+    } else {
+        None
+    }
+}
+
+/// Syncs every due subscription (or all of them with `force`) one after another
+/// and sends one summary notification. Returns false if a sync was already running.
+pub fn sync_due(force: bool) -> bool {
+    if SYNCING.swap(true, Ordering::Relaxed) {
+        return false;
+    }
+    let Some(_lock) = FileLock::acquire("subscriptions") else {
+        println!("subscriptions: Another process is syncing");
+        SYNCING.store(false, Ordering::Relaxed);
+        return false;
+    };
+    let configs = match config::load_config(&config::get_config_file_path()) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("subscriptions: Fail to read config {e}");
+            SYNCING.store(false, Ordering::Relaxed);
+            return true;
+        }
+    };
+    let queue: Vec<Subscription> = configs
+        .subscriptions
+        .list
+        .iter()
+        .filter(|s| force || is_due(s))
+        .cloned()
+        .collect();
+
+    let mut new_items = 0;
+    let mut failed: Vec<String> = Vec::new();
+    for sub in queue.iter() {
+        println!("subscriptions: Checking {}", sub.name);
+        match sync_one(&configs, sub) {
+            Some(n) => new_items += n,
+            None => failed.push(sub.name.clone()),
+        }
+    }
+
+    if new_items > 0 || !failed.is_empty() {
+        let mut body = format!(
+            "Subscriptions: {new_items} new item(s) from {} checked",
+            queue.len()
+        );
+        if !failed.is_empty() {
+            body.push_str(&format!("\nFailed: {}", failed.join(", ")));
+        }
+        let _ = notification_summary(&body);
+    }
+    SYNCING.store(false, Ordering::Relaxed);
+    true
+}
+
+/// Background checker for the running app. It re-reads the config every minute so
+/// toggling it or editing the list takes effect without a restart.
+pub fn start_scheduler() {
+    thread::spawn(|| {
+        loop {
+            let enabled = config::load_config(&config::get_config_file_path())
+                .map(|c| c.subscriptions.background)
+                .unwrap_or(false);
+            if enabled {
+                sync_due(false);
+            }
+            thread::sleep(Duration::from_secs(60));
+        }
+    });
+}
@@ -0,0 +1,402 @@
+use crate::ui::shares::cookies;
+use crate::ui::shares::network;
+use crate::ui::shares::sidecars::Keep;
+use eframe::egui::{self, Color32};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+
+/// One wanted language. `auto` accepts the site's automatic captions.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SubPick {
+    pub lang: String,
+    pub auto: bool,
+}
+
+impl SubPick {
+    pub fn label(&self) -> String {
+        if self.auto {
+            format!("{} (auto)", self.lang)
+        } else {
+            self.lang.clone()
+        }
+    }
+    // "en" also takes regional tracks like "en-US".
+    fn matches(&self, track: &SubTrack) -> bool {
+        track.auto == self.auto
+            && (track.lang == self.lang || track.lang.starts_with(&format!("{}-", self.lang)))
+    }
+    // The same rule for `--sub-langs`, which takes full-match regexes.
+    fn pattern(&self) -> String {
+        format!("{}(-.*)?", regex::escape(&self.lang))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SubFormat {
+    Original,
+    Srt,
+    Vtt,
+    Ass,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SubtitleSettings {
+    pub picks: Vec<SubPick>,
+    /// Take only the first pick the video has, instead of all of them.
+    pub fallback: bool,
+    pub keep: Keep,
+    pub convert: SubFormat,
+}
+
+impl Default for SubtitleSettings {
+    fn default() -> Self {
+        Self {
+            picks: Vec::new(),
+            fallback: false,
+            keep: Keep::Embed,
+            convert: SubFormat::Original,
+        }
+    }
+}
+
+impl SubtitleSettings {
+    /// Configs from before multi-language subtitles only have one language and the auto flag.
+    pub fn or_legacy(mut self, lang: &str, auto: bool) -> Self {
+        if self.picks.is_empty() {
+            self.picks.push(SubPick {
+                lang: lang.to_string(),
+                auto,
+            });
+        }
+        self
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SubTrack {
+    pub lang: String,
+    pub name: String,
+    pub auto: bool,
+}
+
+impl SubTrack {
+    fn pick(&self) -> SubPick {
+        SubPick {
+            lang: self.lang.clone(),
+            auto: self.auto,
+        }
+    }
+}
+
+fn tracks_from(info: &Value, key: &str, auto: bool) -> Vec<SubTrack> {
+    let mut tracks: Vec<SubTrack> = info
+        .get(key)
+        .and_then(|v| v.as_object())
+        .into_iter()
+        .flatten()
+        .filter(|(lang, _)| lang.as_str() != "live_chat")
+        .map(|(lang, formats)| SubTrack {
+            lang: lang.clone(),
+            name: formats
+                .as_array()
+                .and_then(|f| f.first())
+                .and_then(|f| f.get("name"))
+                .and_then(|n| n.as_str())
+                .unwrap_or(lang)
+                .to_string(),
+            auto,
+        })
+        .collect();
+    tracks.sort_by(|a, b| a.lang.cmp(&b.lang));
+    tracks
+}
+
+/// Manual and automatic subtitle tracks the link really has, from `yt-dlp -J`.
+pub fn fetch_tracks(link: &str) -> Result<Vec<SubTrack>, String> {
+    let mut yt = Command::new("yt-dlp");
+    yt.arg("-J").arg("--skip-download").arg("--no-playlist");
+    let net = network::settings();
+    network::apply_yt_dlp(&mut yt, &net);
+    cookies::apply(&mut yt, link, &net);
+    let output = yt.arg(link).output().map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        let err = String::from_utf8_lossy(&output.stderr);
+        return Err(err
+            .lines()
+            .rfind(|l| l.starts_with("ERROR"))
+            .unwrap_or("yt-dlp failed")
+            .to_string());
+    }
+    let info: Value = serde_json::from_slice(&output.stdout).map_err(|e| e.to_string())?;
+    let mut tracks = tracks_from(&info, "subtitles", false);
+    tracks.extend(tracks_from(&info, "automatic_captions", true));
+    Ok(tracks)
+}
+
+/// With the fallback chain on, keeps only the first pick the video has.
+/// When the list can't be fetched every pick is tried.
+fn resolve(link: &str, subs: &SubtitleSettings) -> Vec<SubPick> {
+    if !subs.fallback || subs.picks.len() < 2 {
+        return subs.picks.clone();
+    }
+    match fetch_tracks(link) {
+        Ok(tracks) => subs
+            .picks
+            .iter()
+            .find(|p| tracks.iter().any(|t| p.matches(t)))
+            .map(|p| {
+                println!("subtitles: Using {}", p.label());
+                vec![p.clone()]
+            })
+            .unwrap_or_else(|| {
+                println!("subtitles: None of the picked languages are available");
+                Vec::new()
+            }),
+        Err(e) => {
+            println!("subtitles: Fail to list tracks {e}");
+            subs.picks.clone()
+        }
+    }
+}
+
+pub fn apply(yt: &mut Command, link: &str, subs: &SubtitleSettings) {
+    let picks = resolve(link, subs);
+    if picks.is_empty() {
+        return;
+    }
+    // yt-dlp prefers a manual track over the automatic one when both exist.
+    yt.arg("--write-subs");
+    if picks.iter().any(|p| p.auto) {
+        yt.arg("--write-auto-subs");
+    }
+    let langs: Vec<String> = picks.iter().map(|p| p.pattern()).collect();
+    yt.arg("--sub-langs").arg(langs.join(","));
+    match subs.convert {
+        SubFormat::Original => {}
+        SubFormat::Srt => {
+            yt.arg("--convert-subs").arg("srt");
+        }
+        SubFormat::Vtt => {
+            yt.arg("--convert-subs").arg("vtt");
+        }
+        SubFormat::Ass => {
+            yt.arg("--convert-subs").arg("ass");
+        }
+    }
+    if subs.keep.embed() {
+        yt.arg("--embed-subs");
+        // With --write-subs given yt-dlp keeps the files after embedding.
+        if !subs.keep.sidecar() {
+            yt.arg("--compat-options").arg("no-keep-subs");
+        }
+    }
+}
+
+pub enum TrackList {
+    Idle,
+    Loading,
+    Ready(Vec<SubTrack>),
+    Failed(String),
+}
+
+/// Subtitle picks, fallback, embed/sidecar and conversion. `tracks` holds the
+/// list fetched for `link`. Returns true when the settings changed.
+pub fn subtitles_menu(
+    ui: &mut egui::Ui,
+    subs: &mut SubtitleSettings,
+    new_lang: &mut String,
+    link: &str,
+    tracks: &Arc<Mutex<TrackList>>,
+) -> bool {
+    let mut changed = false;
+    let mut remove = None;
+    let mut up = None;
+    for (i, pick) in subs.picks.iter().enumerate() {
+        ui.horizontal(|ui| {
+            if ui.small_button("x").on_hover_text("Remove").clicked() {
+                remove = Some(i);
+            }
+            if ui
+                .add_enabled(i > 0, egui::Button::new("^").small())
+                .on_hover_text("Try earlier")
+                .clicked()
+            {
+                up = Some(i);
+            }
+            ui.label(pick.label());
+        });
+    }
+    if let Some(i) = remove {
+        subs.picks.remove(i);
+        changed = true;
+    }
+    if let Some(i) = up {
+        subs.picks.swap(i, i - 1);
+        changed = true;
+    }
+
+    let mut add = |pick: SubPick, subs: &mut SubtitleSettings| {
+        if !subs.picks.contains(&pick) {
+            subs.picks.push(pick);
+            changed = true;
+        }
+    };
+    ui.horizontal(|ui| {
+        ui.add(
+            egui::TextEdit::singleline(new_lang)
+                .hint_text("de")
+                .desired_width(50.0),
+        );
+        let code = new_lang.trim().to_string();
+        if ui
+            .add_enabled(!code.is_empty(), egui::Button::new("Add"))
+            .clicked()
+        {
+            add(
+                SubPick {
+                    lang: code.clone(),
+                    auto: false,
+                },
+                subs,
+            );
+            new_lang.clear();
+        }
+        if ui
+            .add_enabled(!code.is_empty(), egui::Button::new("Add auto"))
+            .clicked()
+        {
+            add(
+                SubPick {
+                    lang: code,
+                    auto: true,
+                },
+                subs,
+            );
+            new_lang.clear();
+        }
+    });
+
+    let loading = matches!(*tracks.lock().unwrap(), TrackList::Loading);
+    if ui
+        .add_enabled(
+            !link.trim().is_empty() && !loading,
+            egui::Button::new("Fetch tracks from link"),
+        )
+        .clicked()
+    {
+        *tracks.lock().unwrap() = TrackList::Loading;
+        let tracks = tracks.clone();
+        let link = link.trim().to_string();
+        tokio::task::spawn(async move {
+            let result = match fetch_tracks(&link) {
+                Ok(list) => TrackList::Ready(list),
+                Err(e) => TrackList::Failed(e),
+            };
+            *tracks.lock().unwrap() = result;
+        });
+    }
+    match &*tracks.lock().unwrap() {
+        TrackList::Idle => {}
+        TrackList::Loading => {
+            ui.spinner();
+        }
+        TrackList::Failed(e) => {
+            ui.colored_label(Color32::LIGHT_RED, e);
+        }
+        TrackList::Ready(list) if list.is_empty() => {
+            ui.label("No subtitles on this video");
+        }
+        TrackList::Ready(list) => {
+            egui::ScrollArea::vertical()
+                .max_height(200.0)
+                .show(ui, |ui| {
+                    for track in list.iter() {
+                        let pick = track.pick();
+                        let text = format!("{} - {}", pick.label(), track.name);
+                        if subs.picks.contains(&pick) {
+                            ui.colored_label(Color32::LIGHT_BLUE, text);
+                        } else if ui.button(text).clicked() {
+                            add(pick, subs);
+                        }
+                    }
+                });
+        }
+    }
+
+    ui.separator();
+    changed |= ui
+        .checkbox(&mut subs.fallback, "Fallback chain")
+        .on_hover_text("Only download the first language in the list the video has")
+        .changed();
+    ui.horizontal(|ui| {
+        changed |= ui
+            .radio_value(&mut subs.keep, Keep::Embed, "Embed")
+            .changed();
+        changed |= ui
+            .radio_value(&mut subs.keep, Keep::Sidecar, "Sidecar")
+            .changed();
+        changed |= ui.radio_value(&mut subs.keep, Keep::Both, "Both").changed();
+    });
+    ui.horizontal(|ui| {
+        ui.label("Convert: ");
+        changed |= ui
+            .radio_value(&mut subs.convert, SubFormat::Original, "Original")
+            .changed();
+        changed |= ui
+            .radio_value(&mut subs.convert, SubFormat::Srt, "SRT")
+            .changed();
+        changed |= ui
+            .radio_value(&mut subs.convert, SubFormat::Vtt, "VTT")
+            .changed();
+        changed |= ui
+            .radio_value(&mut subs.convert, SubFormat::Ass, "ASS")
+            .changed();
+    });
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(lang: &str, auto: bool) -> SubTrack {
+        SubTrack {
+            lang: lang.to_string(),
+            name: lang.to_string(),
+            auto,
+        }
+    }
+
+    fn pick(lang: &str, auto: bool) -> SubPick {
+        SubPick {
+            lang: lang.to_string(),
+            auto,
+        }
+    }
+
+    #[test]
+    fn regional_tracks_match() {
+        assert!(pick("en", false).matches(&track("en", false)));
+        assert!(pick("en", false).matches(&track("en-US", false)));
+        assert!(!pick("en", false).matches(&track("eng", false)));
+        assert!(!pick("en", false).matches(&track("en", true)));
+        assert!(pick("pt-BR", true).matches(&track("pt-BR", true)));
+    }
+
+    #[test]
+    fn pattern_agrees_with_matches() {
+        for lang in ["en", "en-US", "eng", "pt-BR", "zh-Hans"] {
+            let re = regex::Regex::new(&format!("^(?:{})$", pick("en", false).pattern())).unwrap();
+            assert_eq!(
+                re.is_match(lang),
+                pick("en", false).matches(&track(lang, false)),
+                "{lang}"
+            );
+        }
+        assert_eq!(pick("zh-Hans", false).pattern(), r"zh\-Hans(-.*)?");
+    }
+}
@@ -0,0 +1,280 @@
+use crate::ui::shares::lyrics::{Lrc, TimedLine};
+use crate::ui::shares::network;
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use url::form_urlencoded;
+
+/// What the main lyrics frame holds. The translation is always stored in its
+/// own language-tagged frame as well.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TranslateMode {
+    Original,
+    Translated,
+    /// Each line followed by its translation.
+    Bilingual,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    Off,
+    Google,
+    LibreTranslate,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TranslateSettings {
+    pub backend: Backend,
+    pub mode: TranslateMode,
+    /// LibreTranslate server, e.g. http://localhost:5000
+    pub libre_url: String,
+    pub libre_key: String,
+}
+
+impl Default for TranslateSettings {
+    fn default() -> Self {
+        Self {
+            backend: Backend::Google,
+            mode: TranslateMode::Original,
+            libre_url: "http://localhost:5000".to_string(),
+            libre_key: String::new(),
+        }
+    }
+}
+
+// Text per Google request before URL encoding, well under URL length limits.
+const CHUNK_CHARS: usize = 1500;
+
+/// The same lyrics with every line translated and the timestamps untouched.
+pub fn translate_lrc(lrc: &Lrc, to: &str, settings: &TranslateSettings) -> Result<Lrc, String> {
+    let texts: Vec<String> = if lrc.synced() {
+        lrc.lines.iter().map(|l| l.text.clone()).collect()
+    } else {
+        lrc.plain.clone()
+    };
+    let translated = translate_lines(&texts, to, settings)?;
+    let mut out = lrc.clone();
+    if out.synced() {
+        for (line, text) in out.lines.iter_mut().zip(translated) {
+            line.text = text;
+        }
+    } else {
+        out.plain = translated;
+    }
+    Ok(out)
+}
+
+/// Builds the main lyrics for the chosen mode.
+pub fn combine(original: &Lrc, translated: &Lrc, mode: TranslateMode) -> Lrc {
+    match mode {
+        TranslateMode::Original => original.clone(),
+        TranslateMode::Translated => translated.clone(),
+        TranslateMode::Bilingual => {
+            let mut out = original.clone();
+            out.lines.clear();
+            out.plain.clear();
+            for (o, t) in original.lines.iter().zip(translated.lines.iter()) {
+                out.lines.push(o.clone());
+                if !t.text.is_empty() && t.text != o.text {
+                    out.lines.push(TimedLine {
+                        time_ms: o.time_ms,
+                        text: t.text.clone(),
+                    });
+                }
+            }
+            for (o, t) in original.plain.iter().zip(translated.plain.iter()) {
+                out.plain.push(o.clone());
+                if !t.trim().is_empty() && t != o {
+                    out.plain.push(t.clone());
+                }
+            }
+            out
+        }
+    }
+}
+
+/// Translates each line on its own so the result lines up with the input.
+/// Empty lines stay empty and are not sent.
+pub fn translate_lines(
+    lines: &[String],
+    to: &str,
+    settings: &TranslateSettings,
+) -> Result<Vec<String>, String> {
+    let mut out: Vec<String> = lines.to_vec();
+    let todo: Vec<usize> = (0..lines.len())
+        .filter(|i| !lines[*i].trim().is_empty())
+        .collect();
+    let results = match settings.backend {
+        Backend::Off => return Ok(out),
+        Backend::Google => {
+            let mut results = Vec::new();
+            for chunk in chunks(&todo, lines) {
+                let texts: Vec<&str> = chunk.iter().map(|i| lines[*i].as_str()).collect();
+                results.extend(google_chunk(&texts, to)?);
+            }
+            results
+        }
+        Backend::LibreTranslate => {
+            let texts: Vec<&str> = todo.iter().map(|i| lines[*i].as_str()).collect();
+            libre(&texts, to, settings)?
+        }
+    };
+    for (i, text) in todo.into_iter().zip(results) {
+        out[i] = text;
+    }
+    Ok(out)
+}
+
+fn chunks(todo: &[usize], lines: &[String]) -> Vec<Vec<usize>> {
+    let mut chunks: Vec<Vec<usize>> = Vec::new();
+    let mut size = 0;
+    for i in todo.iter() {
+        let len = lines[*i].len() + 1;
+        match chunks.last_mut() {
+            Some(last) if size + len <= CHUNK_CHARS => last.push(*i),
+            _ => {
+                chunks.push(vec![*i]);
+                size = 0;
+            }
+        }
+        size += len;
+    }
+    chunks
+}
+
+// One request for the chunk, lines joined by newlines. If the answer doesn't
+// split back into as many lines, the lines go one by one.
+fn google_chunk(texts: &[&str], to: &str) -> Result<Vec<String>, String> {
+    let joined = google(&texts.join("\n"), to)?;
+    let split: Vec<String> = joined.split('\n').map(|l| l.trim().to_string()).collect();
+    if split.len() == texts.len() {
+        return Ok(split);
+    }
+    println!("translate: Line count changed, translating line by line");
+    texts.iter().map(|t| google(t, to)).collect()
+}
+
+fn google(text: &str, to: &str) -> Result<String, String> {
+    let q: String = form_urlencoded::byte_serialize(text.as_bytes()).collect();
+    let url = format!(
+        "https://translate.googleapis.com/translate_a/single?client=gtx&sl=auto&tl={}&dt=t&q={}",
+        to, q
+    );
+    let body = network::get(&url)
+        .map_err(|e| e.to_string())?
+        .body_mut()
+        .read_to_string()
+        .map_err(|e| e.to_string())?;
+    let values = serde_json::from_str::<Value>(&body).map_err(|e| e.to_string())?;
+    let Some(list) = values.get(0).and_then(|v| v.as_array()) else {
+        return Err("unexpected Google response".to_string());
+    };
+    Ok(list
+        .iter()
+        .filter_map(|v| v.get(0).and_then(|v| v.as_str()))
+        .collect::<Vec<_>>()
+        .join(""))
+}
+
+// LibreTranslate takes a list for `q` and answers with a list in the same order.
+fn libre(texts: &[&str], to: &str, settings: &TranslateSettings) -> Result<Vec<String>, String> {
+    if texts.is_empty() {
+        return Ok(Vec::new());
+    }
+    let url = format!(
+        "{}/translate",
+        settings.libre_url.trim().trim_end_matches('/')
+    );
+    let mut body = json!({
+        "q": texts,
+        "source": "auto",
+        "target": to,
+        "format": "text",
+    });
+    if !settings.libre_key.trim().is_empty() {
+        body["api_key"] = json!(settings.libre_key.trim());
+    }
+    let value = network::post_json(&url, &body)
+        .map_err(|e| e.to_string())?
+        .body_mut()
+        .read_json::<Value>()
+        .map_err(|e| e.to_string())?;
+    let Some(list) = value.get("translatedText").and_then(|v| v.as_array()) else {
+        return Err(value
+            .get("error")
+            .and_then(|e| e.as_str())
+            .unwrap_or("unexpected LibreTranslate response")
+            .to_string());
+    };
+    let out: Vec<String> = list
+        .iter()
+        .map(|v| v.as_str().unwrap_or_default().to_string())
+        .collect();
+    if out.len() != texts.len() {
+        return Err("LibreTranslate returned a different number of lines".to_string());
+    }
+    Ok(out)
+}
+
+/// Returns true when a setting changed.
+pub fn translate_menu(ui: &mut egui::Ui, settings: &mut TranslateSettings) -> bool {
+    let mut changed = false;
+    ui.label("Backend:");
+    ui.horizontal(|ui| {
+        changed |= ui
+            .radio_value(&mut settings.backend, Backend::Off, "Off")
+            .changed();
+        changed |= ui
+            .radio_value(&mut settings.backend, Backend::Google, "Google")
+            .changed();
+        changed |= ui
+            .radio_value(
+                &mut settings.backend,
+                Backend::LibreTranslate,
+                "LibreTranslate",
+            )
+            .changed();
+    });
+    if settings.backend == Backend::LibreTranslate {
+        egui::Grid::new("libre_grid").show(ui, |ui| {
+            ui.label("URL: ");
+            changed |= ui
+                .add(
+                    egui::TextEdit::singleline(&mut settings.libre_url)
+                        .hint_text("http://localhost:5000"),
+                )
+                .changed();
+            ui.end_row();
+            ui.label("API key: ");
+            changed |= ui
+                .add(egui::TextEdit::singleline(&mut settings.libre_key).hint_text("optional"))
+                .changed();
+            ui.end_row();
+        });
+    }
+    ui.add_enabled_ui(settings.backend != Backend::Off, |ui| {
+        ui.label("Main lyrics:");
+        changed |= ui
+            .radio_value(&mut settings.mode, TranslateMode::Original, "Original only")
+            .changed();
+        changed |= ui
+            .radio_value(
+                &mut settings.mode,
+                TranslateMode::Translated,
+                "Translated only",
+            )
+            .changed();
+        changed |= ui
+            .radio_value(
+                &mut settings.mode,
+                TranslateMode::Bilingual,
+                "Both, line by line",
+            )
+            .changed();
+        ui.small("The translation is also saved in its own lyrics frame");
+    });
+    changed
+}
@@ -1,5 +1,7 @@
+use crate::ui::shares::network;
 use serde::Deserialize;
 use std::error::Error;
+use std::time::Duration;
 
 pub fn version_check() -> i8 {
     let version: Vec<i8> = env!("CARGO_PKG_VERSION")
@@ -41,7 +43,13 @@ fn match_version(cur_ver: Vec<i8>, git_ver: Option<Vec<i8>>) -> Option<bool> {
 }
 
 fn fetch() -> Result<Vec<i8>, Box<dyn Error>> {
-    let re = ureq::get("https://api.github.com/repos/musdx/azul-box/releases/latest")
+    // Runs before the first frame is painted: one short try, no retries.
+    let (_, agent) = network::agent();
+    let re = agent
+        .get("https://api.github.com/repos/musdx/azul-box/releases/latest")
+        .config()
+        .timeout_global(Some(Duration::from_secs(5)))
+        .build()
         .call()?
         .body_mut()
         .read_json::<Github>()?;
@@ -0,0 +1,236 @@
+use crate::ui::shares::archive::ArchiveTool;
+use crate::ui::shares::audio_format::AudioFormat;
+use crate::ui::shares::config::{self, Subscription};
+use crate::ui::shares::notify::button_sound;
+use crate::ui::shares::subscriptions::{is_due, is_syncing, sync_due};
+use eframe::egui::{self, Color32};
+use native_dialog::DialogBuilder;
+use std::path::PathBuf;
+
+pub struct Subscriptions {
+    pub name: String,
+    pub url: String,
+    pub tool: ArchiveTool,
+    pub preset: i8,
+    pub audio: AudioFormat,
+    pub out_directory: String,
+    pub interval: u32,
+    pub background: bool,
+    pub list: Vec<Subscription>,
+    pub config_path: PathBuf,
+}
+
+impl Default for Subscriptions {
+    fn default() -> Self {
+        let default_directory = dirs::audio_dir()
+            .map(|path| path.to_string_lossy().into_owned())
+            .unwrap_or_else(|| String::from(""));
+        let path = config::get_config_file_path();
+        let configs = match config::load_config(&path) {
+            Ok(config) => config,
+            Err(e) => {
+                println!("subscriptions: Fail to read config {e}");
+                config::Config::default()
+            }
+        };
+        Self {
+            name: String::new(),
+            url: String::new(),
+            tool: ArchiveTool::Music,
+            preset: 1,
+            audio: configs.music_dl.format,
+            out_directory: default_directory,
+            interval: 24,
+            background: configs.subscriptions.background,
+            list: configs.subscriptions.list,
+            config_path: path,
+        }
+    }
+}
+
+const VIDEO_PRESETS: [(&str, i8); 2] = [("MKV", 1), ("MP4", 2)];
+
+fn video_preset_name(preset: i8) -> &'static str {
+    VIDEO_PRESETS
+        .into_iter()
+        .find(|(_, n)| *n == preset)
+        .map(|(name, _)| name)
+        .unwrap_or("?")
+}
+fn preset_name(sub: &Subscription) -> &'static str {
+    match sub.tool {
+        ArchiveTool::Music => sub.audio.name(),
+        ArchiveTool::Video => video_preset_name(sub.preset),
+    }
+}
+
+impl Subscriptions {
+    fn reload(&mut self) {
+        if let Ok(configs) = config::load_config(&self.config_path) {
+            self.list = configs.subscriptions.list;
+        }
+    }
+    fn save_list(&mut self) {
+        let list = self.list.clone();
+        match config::modifier_config(&self.config_path, |cfg| cfg.subscriptions.list = list) {
+            Ok(_) => {
+                println!("subscriptions: Saved list")
+            }
+            Err(e) => {
+                println!("subscriptions: Fail save list {e}")
+            }
+        }
+    }
+    fn tool_button(&mut self, ui: &mut egui::Ui, name: &str, tool: ArchiveTool) {
+        if self.tool == tool {
+            ui.add(egui::Button::new(
+                egui::RichText::new(name).color(Color32::LIGHT_BLUE),
+            ));
+        } else if ui.button(name).clicked() {
+            self.tool = tool;
+            self.preset = 1;
+            self.out_directory = match tool {
+                ArchiveTool::Music => dirs::audio_dir(),
+                ArchiveTool::Video => dirs::video_dir(),
+            }
+            .map(|path| path.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        }
+    }
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            let check = ui.checkbox(&mut self.background, "Check in background");
+            if check.changed() {
+                match config::modifier_config(&self.config_path, |cfg| {
+                    cfg.subscriptions.background = self.background
+                }) {
+                    Ok(_) => {
+                        println!("subscriptions: Changed background")
+                    }
+                    Err(e) => {
+                        println!("subscriptions: Fail change background {e}")
+                    }
+                }
+            }
+            ui.label("Status: ");
+            if is_syncing() {
+                ui.spinner();
+            } else if ui.button("Sync all now").clicked() {
+                button_sound();
+                tokio::task::spawn(async move {
+                    sync_due(true);
+                });
+            }
+        });
+        ui.separator();
+        egui::ScrollArea::vertical()
+            .max_height(200.0)
+            .show(ui, |ui| {
+                let mut remove = None;
+                for (i, sub) in self.list.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        if ui.small_button("x").on_hover_text("Remove").clicked() {
+                            remove = Some(i);
+                        }
+                        let due = if is_due(sub) { " (due)" } else { "" };
+                        ui.label(&sub.name).on_hover_text(format!(
+                            "{}\n{} {} every {}h{}\n{}",
+                            sub.url,
+                            sub.tool.name(),
+                            preset_name(sub),
+                            sub.interval_hours,
+                            due,
+                            sub.directory
+                        ));
+                    });
+                }
+                if let Some(i) = remove {
+                    self.list.remove(i);
+                    self.save_list();
+                }
+            });
+        if ui.small_button("Reload").clicked() {
+            self.reload();
+        }
+        ui.separator();
+        ui.vertical_centered(|ui| {
+            let name_label = ui.label("Name: ");
+            ui.text_edit_singleline(&mut self.name)
+                .labelled_by(name_label.id);
+            let link_label = ui.label("Channel or playlist link: ");
+            ui.text_edit_singleline(&mut self.url)
+                .labelled_by(link_label.id);
+            ui.horizontal(|ui| {
+                self.tool_button(ui, "Music", ArchiveTool::Music);
+                self.tool_button(ui, "Video", ArchiveTool::Video);
+                match self.tool {
+                    ArchiveTool::Music => {
+                        ui.menu_button(self.audio.name(), |ui| {
+                            for format in AudioFormat::ALL {
+                                if ui.button(format.name()).clicked() {
+                                    self.audio = format;
+                                    ui.close_menu();
+                                }
+                            }
+                        });
+                    }
+                    ArchiveTool::Video => {
+                        ui.menu_button(video_preset_name(self.preset), |ui| {
+                            for (name, n) in VIDEO_PRESETS {
+                                if ui.button(name).clicked() {
+                                    self.preset = n;
+                                    ui.close_menu();
+                                }
+                            }
+                        });
+                    }
+                }
+                ui.add(
+                    egui::DragValue::new(&mut self.interval)
+                        .range(1..=720)
+                        .suffix(" h"),
+                );
+            });
+            let dir_label = ui.label("Directory: ");
+            if ui
+                .text_edit_singleline(&mut self.out_directory)
+                .labelled_by(dir_label.id)
+                .clicked()
+            {
+                let path = DialogBuilder::file()
+                    .set_location(&self.out_directory)
+                    .open_single_dir()
+                    .show()
+                    .unwrap();
+
+                if let Some(p) = path {
+                    self.out_directory = p.to_string_lossy().into_owned();
+                } else {
+                    println!("No file selected.");
+                }
+            };
+            if ui.button("Add").clicked() && !self.url.is_empty() {
+                button_sound();
+                self.reload();
+                let name = if self.name.is_empty() {
+                    self.url.clone()
+                } else {
+                    self.name.clone()
+                };
+                self.list.push(Subscription {
+                    name,
+                    url: self.url.clone(),
+                    tool: self.tool,
+                    preset: self.preset,
+                    audio: self.audio,
+                    directory: self.out_directory.clone(),
+                    interval_hours: self.interval,
+                    last_check: 0,
+                });
+                self.save_list();
+                self.name.clear();
+                self.url.clear();
+            }
+        });
+    }
+}
@@ -46,11 +46,9 @@ impl VideoConvert {
                 self.format_out = name.to_string();
                 ui.close_menu();
             };
-        } else if self.format_in != name {
-            if ui.button(name).clicked() {
-                self.format_out = name.to_string();
-                ui.close_menu();
-            };
+        } else if self.format_in != name && ui.button(name).clicked() {
+            self.format_out = name.to_string();
+            ui.close_menu();
         }
     }
     pub fn ui(&mut self, ui: &mut egui::Ui) {
@@ -157,11 +155,9 @@ impl VideoConvert {
                         }
                     });
                 }
-            } else if self.status.load(Ordering::Relaxed) == 1 {
-                if ui.button("Cancel").clicked() {
-                    button_sound();
-                    let _ = Command::new("pkill").arg("ffmpeg").output();
-                }
+            } else if self.status.load(Ordering::Relaxed) == 1 && ui.button("Cancel").clicked() {
+                button_sound();
+                let _ = Command::new("pkill").arg("ffmpeg").output();
             }
         });
     }
@@ -1,9 +1,17 @@
-use crate::ui::shares::lang::LangThing;
+use crate::ui::shares::archive::{self, ArchiveTool};
+use crate::ui::shares::cookies;
+use crate::ui::shares::live::{self, LiveProgress, LiveSettings};
+use crate::ui::shares::network;
+use crate::ui::shares::process::Running;
+use crate::ui::shares::sections;
+use crate::ui::shares::sidecars::{self, Sidecars};
+use crate::ui::shares::sponsorblock::{self, SponsorBlock};
+use crate::ui::shares::subtitles::{self, SubtitleSettings, TrackList};
 use eframe::egui::{self, Color32};
 use native_dialog::DialogBuilder;
 use std::process::Command;
-use std::sync::Arc;
 use std::sync::atomic::{AtomicI8, Ordering};
+use std::sync::{Arc, Mutex};
 
 use crate::ui::shares::notify::{
     button_sound, done_sound, fail_sound, notification_done, notification_fail,
@@ -18,8 +26,19 @@ pub struct VideoDownload {
     pub format: i8,
     pub frag: i8,
     pub subtitle: bool,
-    pub sub_lang: String,
-    pub auto_sub: bool,
+    pub subs: SubtitleSettings,
+    pub new_sub_lang: String,
+    pub sub_tracks: Arc<Mutex<TrackList>>,
+    pub archive: bool,
+    pub sponsorblock: SponsorBlock,
+    pub sponsorblock_api: String,
+    pub sections: String,
+    pub exact_cuts: bool,
+    pub live: bool,
+    pub live_settings: LiveSettings,
+    pub live_progress: Arc<LiveProgress>,
+    pub sidecars: Sidecars,
+    pub running: Running,
     pub config_path: PathBuf,
 }
 
@@ -41,12 +60,26 @@ impl Default for VideoDownload {
         Self {
             link: String::new(),
             out_directory: default_directory,
-            status: Arc::new(AtomicI8::new(0)), // 0 = nothing / 1 = pending / 2 = Done / 3 = Fail
+            status: Arc::new(AtomicI8::new(0)), // 0 = nothing / 1 = pending / 2 = Done / 3 = Fail / 4 = Needs login
             format: configs.video_dl.format,
             frag: configs.video_dl.fragments,
             subtitle: configs.video_dl.subtitle,
-            sub_lang: configs.universal.language,
-            auto_sub: configs.video_dl.auto_gen_sub,
+            subs: configs
+                .video_dl
+                .subtitles
+                .or_legacy(&configs.universal.language, configs.video_dl.auto_gen_sub),
+            new_sub_lang: String::new(),
+            sub_tracks: Arc::new(Mutex::new(TrackList::Idle)),
+            archive: configs.video_dl.archive,
+            sponsorblock: configs.video_dl.sponsorblock,
+            sponsorblock_api: configs.universal.sponsorblock_api,
+            sections: String::new(),
+            exact_cuts: configs.video_dl.exact_cuts,
+            live: false,
+            live_settings: configs.video_dl.live,
+            live_progress: Arc::new(LiveProgress::default()),
+            sidecars: configs.video_dl.sidecars,
+            running: Running::default(),
             config_path: path,
         }
     }
@@ -56,68 +89,121 @@ impl VideoDownload {
     fn start_download_status(&mut self) {
         self.status.store(1, Ordering::Relaxed);
     }
-    fn format_button(&mut self, ui: &mut egui::Ui, name: &str, numbername: i8) {
-        if self.format == numbername {
-            if ui
-                .add(egui::Button::new(
-                    egui::RichText::new(name).color(Color32::LIGHT_BLUE),
-                ))
-                .clicked()
-            {
-                self.format = numbername;
-                ui.close_menu();
-            };
-        } else {
-            if ui.button(name).clicked() {
-                self.format = numbername;
+    fn job(&self) -> VideoJob {
+        VideoJob {
+            link: self.link.clone(),
+            directory: self.out_directory.clone(),
+            format: self.format,
+            frags: self.frag,
+            subtitle: self.subtitle,
+            subs: self.subs.clone(),
+            archive: self.archive,
+            sponsorblock: self.sponsorblock.clone(),
+            sponsorblock_api: self.sponsorblock_api.clone(),
+            sections: sections::parse_sections(&self.sections).unwrap_or_default(),
+            exact_cuts: self.exact_cuts,
+            live: self.live.then(|| self.live_settings.clone()),
+            live_progress: self.live_progress.clone(),
+            sidecars: self.sidecars.clone(),
+            notify: true,
+            running: self.running.clone(),
+        }
+    }
+    fn sponsorblock_button(&mut self, ui: &mut egui::Ui) {
+        ui.menu_button("SponsorBlock", |ui| {
+            let mut api_changed = false;
+            if sponsorblock::sponsorblock_menu(
+                ui,
+                &mut self.sponsorblock,
+                &mut self.sponsorblock_api,
+                &mut api_changed,
+            ) {
                 match config::modifier_config(&self.config_path, |cfg| {
-                    cfg.video_dl.format = self.format
+                    cfg.video_dl.sponsorblock = self.sponsorblock.clone()
                 }) {
                     Ok(_) => {
-                        println!("video_dl: Changed format")
+                        println!("video_dl: Changed sponsorblock")
                     }
                     Err(e) => {
-                        println!("video_dl: Fail change format {e}")
+                        println!("video_dl: Fail change sponsorblock {e}")
                     }
                 }
-                ui.close_menu();
-            };
-        }
-    }
-    fn auto_on(&mut self, ui: &mut egui::Ui) {
-        if self.auto_sub {
-            if ui
-                .add(egui::Button::new(
-                    egui::RichText::new("Auto generated").color(Color32::LIGHT_BLUE),
-                ))
-                .clicked()
-            {
-                self.auto_sub = false;
+            }
+            if api_changed {
                 match config::modifier_config(&self.config_path, |cfg| {
-                    cfg.video_dl.auto_gen_sub = self.auto_sub
+                    cfg.universal.sponsorblock_api = self.sponsorblock_api.clone()
                 }) {
                     Ok(_) => {
-                        println!("video_dl: Changed auto_sub")
+                        println!("video_dl: Changed sponsorblock api")
                     }
                     Err(e) => {
-                        println!("video_dl: Fail change auto_sub {e}")
+                        println!("video_dl: Fail change sponsorblock api {e}")
                     }
                 }
             }
-        } else {
-            if ui.button("Auto generated").clicked() {
-                self.auto_sub = true;
+        });
+    }
+    fn sidecars_button(&mut self, ui: &mut egui::Ui) {
+        ui.menu_button("Archival", |ui| {
+            if sidecars::sidecars_menu(ui, &mut self.sidecars) {
                 match config::modifier_config(&self.config_path, |cfg| {
-                    cfg.video_dl.auto_gen_sub = self.auto_sub
+                    cfg.video_dl.sidecars = self.sidecars.clone()
                 }) {
                     Ok(_) => {
-                        println!("video_dl: Changed auto_sub")
+                        println!("video_dl: Changed sidecars")
                     }
                     Err(e) => {
-                        println!("video_dl: Fail change auto_sub {e}")
+                        println!("video_dl: Fail change sidecars {e}")
+                    }
+                }
+            }
+        });
+    }
+    fn archive_button(&mut self, ui: &mut egui::Ui) {
+        ui.menu_button("Archive", |ui| {
+            ui.horizontal(|ui| {
+                ui.label("On/Off: ");
+                let check = ui.checkbox(&mut self.archive, "");
+                if check.changed() {
+                    match config::modifier_config(&self.config_path, |cfg| {
+                        cfg.video_dl.archive = self.archive
+                    }) {
+                        Ok(_) => {
+                            println!("video_dl: Changed archive")
+                        }
+                        Err(e) => {
+                            println!("video_dl: Fail change archive {e}")
+                        }
                     }
                 }
+            });
+            archive::archive_ui(ui, ArchiveTool::Video);
+        });
+    }
+    fn format_button(&mut self, ui: &mut egui::Ui, name: &str, numbername: i8) {
+        if self.format == numbername {
+            if ui
+                .add(egui::Button::new(
+                    egui::RichText::new(name).color(Color32::LIGHT_BLUE),
+                ))
+                .clicked()
+            {
+                self.format = numbername;
+                ui.close_menu();
+            };
+        } else if ui.button(name).clicked() {
+            self.format = numbername;
+            match config::modifier_config(&self.config_path, |cfg| {
+                cfg.video_dl.format = self.format
+            }) {
+                Ok(_) => {
+                    println!("video_dl: Changed format")
+                }
+                Err(e) => {
+                    println!("video_dl: Fail change format {e}")
+                }
             }
+            ui.close_menu();
         }
     }
     pub fn ui(&mut self, ui: &mut egui::Ui) {
@@ -145,9 +231,24 @@ impl VideoDownload {
                                 }
                             }
                         });
-                        let lang_in = self.sub_lang.clone();
-                        self.sub_lang = LangThing::lang_chooser(ui, lang_in);
-                        self.auto_on(ui);
+                        if subtitles::subtitles_menu(
+                            ui,
+                            &mut self.subs,
+                            &mut self.new_sub_lang,
+                            &self.link,
+                            &self.sub_tracks,
+                        ) {
+                            match config::modifier_config(&self.config_path, |cfg| {
+                                cfg.video_dl.subtitles = self.subs.clone()
+                            }) {
+                                Ok(_) => {
+                                    println!("video_dl: Changed subtitles")
+                                }
+                                Err(e) => {
+                                    println!("video_dl: Fail change subtitles {e}")
+                                }
+                            }
+                        }
                     } else {
                         ui.horizontal(|ui| {
                             ui.label("On/Off: ");
@@ -167,6 +268,9 @@ impl VideoDownload {
                         });
                     }
                 });
+                self.sponsorblock_button(ui);
+                self.archive_button(ui);
+                self.sidecars_button(ui);
 
                 let c =
                     ui.add(egui::widgets::Slider::new(&mut self.frag, 1..=10).text("Fragments"));
@@ -193,6 +297,9 @@ impl VideoDownload {
                 ui.colored_label(Color32::LIGHT_GREEN, "Done!");
             } else if self.status.load(Ordering::Relaxed) == 3 {
                 ui.colored_label(Color32::LIGHT_RED, "Fail!");
+            } else if self.status.load(Ordering::Relaxed) == 4 {
+                ui.colored_label(Color32::LIGHT_RED, "Authentication required")
+                    .on_hover_text("Add a cookie profile for this site in Cookies");
             }
         });
         ui.separator();
@@ -201,6 +308,33 @@ impl VideoDownload {
             ui.text_edit_singleline(&mut self.link)
                 .labelled_by(link_label.id);
 
+            ui.checkbox(&mut self.live, "Live stream / premiere");
+            if self.live {
+                if live::live_ui(ui, &mut self.live_settings) {
+                    match config::modifier_config(&self.config_path, |cfg| {
+                        cfg.video_dl.live = self.live_settings.clone()
+                    }) {
+                        Ok(_) => {
+                            println!("video_dl: Changed live")
+                        }
+                        Err(e) => {
+                            println!("video_dl: Fail change live {e}")
+                        }
+                    }
+                }
+            } else if sections::sections_ui(ui, &mut self.sections, &mut self.exact_cuts) {
+                match config::modifier_config(&self.config_path, |cfg| {
+                    cfg.video_dl.exact_cuts = self.exact_cuts
+                }) {
+                    Ok(_) => {
+                        println!("video_dl: Changed exact cuts")
+                    }
+                    Err(e) => {
+                        println!("video_dl: Fail change exact cuts {e}")
+                    }
+                }
+            }
+
             let dir_label = ui.label("Directory: ");
             if ui
                 .text_edit_singleline(&mut self.out_directory)
@@ -221,22 +355,19 @@ impl VideoDownload {
             };
 
             if self.status.load(Ordering::Relaxed) != 1 {
-                if ui.button("Download").clicked() {
+                let valid = self.live || sections::parse_sections(&self.sections).is_ok();
+                if ui
+                    .add_enabled(valid, egui::Button::new("Download"))
+                    .clicked()
+                {
                     button_sound();
                     self.start_download_status();
 
-                    let link = self.link.clone();
-                    let directory = self.out_directory.clone();
-                    let format = self.format;
-                    let frags = self.frag;
+                    let job = self.job();
                     let progress = self.status.clone();
-                    let subtile = self.subtitle;
-                    let lang = self.sub_lang.clone();
-                    let auto_gen = self.auto_sub;
 
                     tokio::task::spawn(async move {
-                        let status =
-                            download(link, directory, format, frags, subtile, &lang, auto_gen);
+                        let status = download(job);
                         progress.store(status, Ordering::Relaxed);
                         if status == 2 {
                             done_sound();
@@ -245,62 +376,155 @@ impl VideoDownload {
                         }
                     });
                 }
-            } else if self.status.load(Ordering::Relaxed) == 1 {
-                if ui.button("Cancel").clicked() {
+            } else if self.live {
+                ui.label(self.live_progress.text());
+                // SIGINT lets yt-dlp finish the file and remux it instead of dropping it.
+                if ui.button("Stop recording").clicked() {
                     button_sound();
-                    let _ = Command::new("pkill").arg("yt-dlp").output();
+                    self.running.interrupt();
                 }
+            } else if ui.button("Cancel").clicked() {
+                button_sound();
+                self.running.kill();
             }
         });
     }
 }
 
-fn download(
-    link: String,
-    directory: String,
-    format: i8,
-    frag: i8,
-    sub: bool,
-    lang: &str,
-    auto_gen: bool,
-) -> i8 {
-    let n = frag.to_string().to_owned();
+#[derive(Debug, Clone)]
+pub struct VideoJob {
+    pub link: String,
+    pub directory: String,
+    pub format: i8,
+    pub frags: i8,
+    pub subtitle: bool,
+    pub subs: SubtitleSettings,
+    pub archive: bool,
+    pub sponsorblock: SponsorBlock,
+    pub sponsorblock_api: String,
+    pub sections: Vec<String>,
+    pub exact_cuts: bool,
+    pub live: Option<LiveSettings>,
+    pub live_progress: Arc<LiveProgress>,
+    pub sidecars: Sidecars,
+    pub notify: bool,
+    pub running: Running,
+}
+
+impl VideoJob {
+    /// Builds a job from the saved Video-dl settings, for downloads started
+    /// outside the window (subscriptions).
+    pub fn from_config(configs: &config::Config, link: &str, directory: &str, format: i8) -> Self {
+        Self {
+            link: link.to_string(),
+            directory: directory.to_string(),
+            format,
+            frags: configs.video_dl.fragments,
+            subtitle: configs.video_dl.subtitle,
+            subs: configs
+                .video_dl
+                .subtitles
+                .clone()
+                .or_legacy(&configs.universal.language, configs.video_dl.auto_gen_sub),
+            archive: true,
+            sponsorblock: configs.video_dl.sponsorblock.clone(),
+            sponsorblock_api: configs.universal.sponsorblock_api.clone(),
+            sections: Vec::new(),
+            exact_cuts: configs.video_dl.exact_cuts,
+            live: None,
+            live_progress: Arc::new(LiveProgress::default()),
+            sidecars: configs.video_dl.sidecars.clone(),
+            notify: false,
+            running: Running::default(),
+        }
+    }
+}
+
+pub fn download(job: VideoJob) -> i8 {
+    let n = job.frags.to_string();
 
     let mut yt = Command::new("yt-dlp");
     yt.arg("--concurrent-fragments")
         .arg(n)
-        .arg("--embed-thumbnail")
         .arg("--embed-metadata")
-        .current_dir(directory);
-    if sub && auto_gen {
-        yt.arg("--write-auto-subs")
-            .arg("--embed-subs")
-            .arg("--sub-lang")
-            .arg(lang);
-    } else if sub {
-        yt.arg("--embed-subs").arg("--sub-lang").arg(lang);
+        .current_dir(&job.directory);
+    let printed = if job.sidecars.enabled {
+        Some(sidecars::apply(&mut yt, &job.link, &job.sidecars))
+    } else {
+        yt.arg("--embed-thumbnail");
+        None
+    };
+    // A clip isn't the video, a later full download must not be skipped.
+    let archive = job.archive && job.sections.is_empty();
+    let moved = archive::apply(&mut yt, ArchiveTool::Video, archive);
+    let net = network::settings();
+    network::apply_yt_dlp(&mut yt, &net);
+    cookies::apply(&mut yt, &job.link, &net);
+    sponsorblock::apply(&mut yt, &job.sponsorblock, &job.sponsorblock_api);
+    if let Some(live) = &job.live {
+        live::apply(&mut yt, live, job.format);
+    } else {
+        sections::apply(&mut yt, &job.sections, job.exact_cuts);
+    }
+    if job.live.is_none() && !job.sections.is_empty() {
+        yt.arg("--output").arg(sections::output_template(
+            "%(title)s [%(id)s].%(ext)s",
+            &job.sections,
+        ));
+    }
+    if job.sidecars.enabled {
+        // Archival mode already asks for the subtitle tracks.
+    } else if job.subtitle {
+        subtitles::apply(&mut yt, &job.link, &job.subs);
     }
 
-    if format == 1 {
+    if job.format == 1 {
         yt.arg("-f").arg("bestvideo+bestaudio");
-    } else if format == 2 {
+    } else if job.format == 2 {
         yt.arg("-f")
             .arg("bestvideo[ext=mp4]+bestaudio[ext=m4a]/best[ext=mp4]/best");
     }
-    let output = yt.arg(link).output().expect("Pls some thing");
-    let log = String::from_utf8(output.stdout).unwrap_or_else(|_| "Life suck".to_string());
+    yt.arg(&job.link);
+    let (log, err_log) = match &job.live {
+        Some(live) => live::record(
+            &mut yt,
+            live,
+            &job.directory,
+            &job.live_progress,
+            &job.running,
+        ),
+        None => {
+            let output = job.running.output(&mut yt).expect("Pls some thing");
+            (
+                String::from_utf8(output.stdout).unwrap_or_else(|_| "Life suck".to_string()),
+                String::from_utf8_lossy(&output.stderr).into_owned(),
+            )
+        }
+    };
     println!("{log}");
+    eprintln!("{err_log}");
 
-    let status: i8 = if log.contains("[EmbedThumbnail]") {
+    let files = archive::collect(ArchiveTool::Video, &moved, archive);
+    if let Some(printed) = printed {
+        sidecars::finish(&printed, &job.sidecars);
+    }
+    let status: i8 = if !files.is_empty()
+        || log.contains("[EmbedThumbnail]")
+        || log.contains("has already been recorded in the archive")
+    {
         2
+    } else if cookies::auth_required(&err_log) {
+        4
     } else {
         3
     };
 
-    if status == 2 {
-        let _ = notification_done("video downloader");
-    } else {
-        let _ = notification_fail("video downloader");
+    if job.notify {
+        if status == 2 {
+            let _ = notification_done("video downloader");
+        } else {
+            let _ = notification_fail("video downloader");
+        }
     }
     status
 }